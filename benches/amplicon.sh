@@ -0,0 +1,103 @@
+#!/usr/bin/env bash
+# Benchmarks `amplicon` with primer panels against a random genome, comparing the current
+# build with a baseline build of another git revision on the same input.
+#
+# Usage: benches/amplicon.sh <baseline_ref> [genome_size] [num_primer_pairs] [binary]
+#
+# The baseline is built in a temporary shared clone, so the checkout is left untouched.
+set -euo pipefail
+
+if [[ $# -lt 1 ]]; then
+    echo "usage: $0 <baseline_ref> [genome_size] [num_primer_pairs] [binary]" >&2
+    exit 1
+fi
+
+BASELINE_REF=$1
+GENOME_SIZE=${2:-5000000}
+NUM_PAIRS=${3:-200}
+BIN=${4:-target/release/fasta_rs}
+
+# Resolved here, since refs such as origin/main are not available in the clone.
+BASELINE_SHA=$(git rev-parse --verify --quiet "$BASELINE_REF^{commit}") || {
+    echo "unknown baseline revision: $BASELINE_REF" >&2
+    exit 1
+}
+
+WORKDIR=$(mktemp -d)
+trap 'rm -rf "$WORKDIR"' EXIT
+
+# Baseline binary, built in a temporary clone with its own target directory.
+git clone --quiet --shared --no-checkout "$(git rev-parse --show-toplevel)" "$WORKDIR/baseline"
+git -C "$WORKDIR/baseline" checkout --quiet --detach "$BASELINE_SHA"
+cargo build --quiet --release --manifest-path "$WORKDIR/baseline/Cargo.toml" --target-dir "$WORKDIR/target"
+BASELINE_BIN="$WORKDIR/target/release/fasta_rs"
+
+# Random genome split into 60 bp lines, with a fixed seed for reproducibility.
+awk -v n="$GENOME_SIZE" 'BEGIN {
+    srand(42); split("A C G T", nt, " ");
+    print ">genome";
+    for (i = 0; i < n; i += 60) {
+        line = "";
+        for (j = 0; j < 60 && i + j < n; j++) line = line nt[int(rand() * 4) + 1];
+        print line;
+    }
+}' > "$WORKDIR/genome.fasta"
+
+# Primer pairs sampled from the genome, with inserts of 200-1000 bp. In the shared panel,
+# each forward primer is paired with `SHARED` reverse primers downstream of it, as in tiling
+# and nested designs.
+make_primers() {
+    awk -v pairs="$NUM_PAIRS" -v shared="$1" '
+function rc(s,    i, out, c) {
+    out = "";
+    for (i = length(s); i > 0; i--) {
+        c = substr(s, i, 1);
+        out = out (c == "A" ? "T" : c == "C" ? "G" : c == "G" ? "C" : "A");
+    }
+    return out;
+}
+!/^>/ { seq = seq $0 }
+END {
+    srand(7);
+    for (p = 0; p < pairs; p += shared) {
+        start = 1 + int(rand() * (length(seq) - 1100));
+        for (k = 0; k < shared && p + k < pairs; k++) {
+            insert = 200 + int(rand() * 800);
+            printf "pair_%d\t%s\t%s\t100\t1200\t2\n", p + k, substr(seq, start, 20), rc(substr(seq, start + 20 + insert, 20));
+        }
+    }
+}' "$WORKDIR/genome.fasta"
+}
+
+make_primers 1 > "$WORKDIR/unique.tsv"
+make_primers 5 > "$WORKDIR/shared.tsv"
+
+# Prints the run time in seconds of the given command.
+run_time() {
+    local start end
+    start=$(date +%s.%N)
+    "$@"
+    end=$(date +%s.%N)
+    awk -v s="$start" -v e="$end" 'BEGIN { printf "%.2f", e - s }'
+}
+
+printf "panel\tsearch_type\tamplicons\tbaseline\tcurrent\tspeedup\n"
+
+for panel in unique shared; do
+    for search_type in exact fuzzy; do
+        args=(amplicon -f "$WORKDIR/genome.fasta" -p "$WORKDIR/$panel.tsv" -s "$search_type")
+
+        baseline=$(run_time "$BASELINE_BIN" "${args[@]}" -o "$WORKDIR/baseline.tsv")
+        current=$(run_time "$BIN" "${args[@]}" -o "$WORKDIR/current.tsv")
+
+        # Both builds must find the same amplicons, regardless of output order.
+        if ! cmp -s <(sort "$WORKDIR/baseline.tsv") <(sort "$WORKDIR/current.tsv"); then
+            echo "amplicons differ between baseline and current for $panel $search_type" >&2
+            exit 1
+        fi
+
+        num_amplicons=$(($(wc -l < "$WORKDIR/current.tsv") - 1))
+        awk -v p="$panel" -v t="$search_type" -v n="$num_amplicons" -v b="$baseline" -v c="$current" \
+            'BEGIN { printf "%s\t%s\t%d\t%.2fs\t%.2fs\t%.1fx\n", p, t, n, b, c, b / c }'
+    done
+done
@@ -1,330 +1,660 @@
-use crate::args::SearchType;
+use crate::amplicon::primers::{PrimerPair, parse_primer_file};
+use crate::args::{AmpliconFormat, SearchType};
 use crate::errors::AppError;
-use bio::pattern_matching::myers::MyersBuilder;
-use bio_utils_rs::io::{bio_fasta_reader, get_bufwriter};
-use bio_utils_rs::nucleotide::reverse_complement;
-use memchr::memmem;
+use crate::fastx::{FastxWriter, Header, WriterOpts, get_writer, read_records};
+use crate::iupac::{AMBIGUOUS_CODES, find_all, reverse_complement};
+use bio::alignment::AlignmentOperation;
+use bio::pattern_matching::myers::{Myers, MyersBuilder};
 use rayon::prelude::*;
 use rstest::*;
-use std::collections::HashSet;
-use std::path::PathBuf;
-
-use std::{
-    fs::File,
-    io::{BufRead, BufReader, Write},
-};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Mismatches (edit distance) of each primer, with mismatch positions counted
+/// from the 3' end of the primer (1 = 3' terminal base).
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct PrimerMismatches {
+    pub forward: usize,
+    pub reverse: usize,
+    pub forward_3p: Vec<usize>,
+    pub reverse_3p: Vec<usize>,
+}
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Default)]
 pub struct AmpliconResult<'a> {
     pub amplicon: &'a [u8],
     pub start: usize,
     pub end: usize,
+    /// Start of the forward primer binding site.
+    pub forward_start: usize,
+    /// End of the reverse primer binding site.
+    pub reverse_end: usize,
     pub insert_length: usize,
     pub total_length: usize,
+    pub mismatches: PrimerMismatches,
 }
-pub struct PrimerPair {
-    pub primer_name: String,
-    pub forward_primer: Vec<u8>,
-    pub reverse_primer: Vec<u8>,
-    pub min_len: usize,
-    pub max_len: usize,
-    pub num_mismatch: Option<usize>,
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Strand {
+    Forward,
+    Reverse,
 }
 
-fn extract_primer_info(primer_line: &str) -> Result<PrimerPair, AppError> {
-    let line_vec: Vec<&str> = primer_line.split("\t").map(|l| l.trim()).collect();
+impl Strand {
+    pub fn symbol(&self) -> char {
+        match self {
+            Strand::Forward => '+',
+            Strand::Reverse => '-',
+        }
+    }
+}
 
-    if line_vec.len() < 5 {
-        return Err(AppError::PrimerLineFormatError(primer_line.to_owned()));
+/// Amplicon found on either strand. Coordinates are on the forward strand,
+/// while the sequence is in primer orientation (forward primer first).
+#[derive(PartialEq, Debug)]
+pub struct StrandedAmplicon {
+    pub amplicon: Vec<u8>,
+    pub start: usize,
+    pub end: usize,
+    pub strand: Strand,
+    pub insert_length: usize,
+    pub total_length: usize,
+    pub mismatches: PrimerMismatches,
+}
+
+fn myers_builder(primer_seq: &[u8]) -> Myers {
+    let mut builder = MyersBuilder::new();
+    for (code, bases) in AMBIGUOUS_CODES {
+        builder.ambig(code, bases);
     }
+    builder.build_64(primer_seq)
+}
 
-    match line_vec.len() {
-        5 => {
-            // TODO - this is not optimal.
-            let primer_name = line_vec[0];
-            let forward_primer = line_vec[1].as_bytes();
-            let reverse_primer = line_vec[2].as_bytes();
-            let min_len = line_vec[3].parse::<usize>()?;
-            let max_len = line_vec[4].parse::<usize>()?;
-
-            let num_mismatch: Option<usize> =
-                line_vec.get(5).map(|s| s.parse::<usize>()).transpose()?;
-
-            Ok(PrimerPair {
-                primer_name: primer_name.to_owned(),
-                forward_primer: forward_primer.to_owned(),
-                reverse_primer: reverse_primer.to_owned(),
-                min_len,
-                max_len,
-                num_mismatch,
-            })
+/// Pattern positions (0-based) of substitutions and indels in a Myers alignment path.
+fn mismatch_positions(ops: &[AlignmentOperation]) -> Vec<usize> {
+    let mut positions: Vec<usize> = Vec::new();
+    let mut i: usize = 0;
+
+    for op in ops {
+        match op {
+            AlignmentOperation::Match => i += 1,
+            // Ins consumes a pattern base that is missing in the text.
+            AlignmentOperation::Subst | AlignmentOperation::Ins => {
+                positions.push(i);
+                i += 1;
+            }
+            AlignmentOperation::Del => positions.push(i),
+            _ => {}
         }
-        _ => Err(AppError::PrimerLineFormatError(primer_line.to_owned())),
     }
+
+    positions.dedup();
+    positions
+}
+
+/// Primer binding site on the searched strand. For the reverse primer, the pattern
+/// is its reverse complement, so `start` is the end of the insert. With indels, the
+/// binding site can be shorter or longer than the primer.
+#[derive(Debug, Clone, PartialEq)]
+struct PrimerHit {
+    start: usize,
+    end: usize,
+    mismatches: usize,
+    three_prime: Vec<usize>,
 }
 
-pub fn parse_primer_file(primer_file: &PathBuf) -> Result<Vec<PrimerPair>, AppError> {
-    let f = File::open(primer_file)?;
+/// Best hit of each binding site. Myers reports overlapping hits ending at neighbouring
+/// positions, with starts that differ by at most `max_dist`. Hits are therefore collapsed
+/// while they start within `max_dist` of the kept hit, keeping the lowest distance and
+/// then the binding site closest to the primer length.
+/// Mismatch positions are converted to 3' positions of the primer, where the
+/// pattern for the reverse primer is its reverse complement.
+fn fuzzy_hits(
+    myers: &mut Myers,
+    seq: &[u8],
+    primer_len: usize,
+    max_dist: u8,
+    is_reverse: bool,
+) -> Vec<PrimerHit> {
+    let mut hits: Vec<PrimerHit> = Vec::new();
+    let mut ops: Vec<AlignmentOperation> = Vec::new();
+
+    let mut matches = myers.find_all(seq, max_dist);
+    while let Some((start, end, dist)) = matches.next_path(&mut ops) {
+        let mut three_prime: Vec<usize> = mismatch_positions(&ops)
+            .into_iter()
+            .map(|i| match is_reverse {
+                true => i + 1,
+                false => primer_len - i.min(primer_len - 1),
+            })
+            .collect();
+        three_prime.sort_unstable();
+
+        hits.push(PrimerHit {
+            start,
+            end,
+            mismatches: dist as usize,
+            three_prime,
+        });
+    }
+
+    // Hits are found in order of their end, so starts are only nearly sorted.
+    hits.sort_by_key(|hit| hit.start);
 
-    let reader = BufReader::new(f);
-    let mut primer_pairs: Vec<PrimerPair> = Vec::new();
+    // Of equal distances, a substitution is preferred over an indel at the end of the primer.
+    let rank = |hit: &PrimerHit| (hit.mismatches, (hit.end - hit.start).abs_diff(primer_len));
 
-    for primer_line in reader.lines().map_while(Result::ok) {
-        match extract_primer_info(&primer_line) {
-            Ok(primer_pair) => primer_pairs.push(primer_pair),
-            Err(_) => continue,
+    let mut best: Vec<PrimerHit> = Vec::new();
+
+    for hit in hits {
+        match best.last_mut() {
+            Some(kept) if hit.start - kept.start <= max_dist as usize => {
+                if rank(&hit) < rank(kept) {
+                    *kept = hit;
+                }
+            }
+            _ => best.push(hit),
         }
     }
 
-    match primer_pairs.len() {
-        0 => Err(AppError::NoPrimersFoundError),
-        _ => Ok(primer_pairs),
-    }
+    best
 }
 
-fn myers_builder(primer_seq: &[u8]) -> bio::pattern_matching::myers::Myers {
-    MyersBuilder::new()
-        .ambig(b'N', b"ACGT")
-        .ambig(b'R', b"AG")
-        .ambig(b'Y', b"CT")
-        .ambig(b'S', b"GC")
-        .ambig(b'W', b"AT")
-        .ambig(b'K', b"GT")
-        .ambig(b'M', b"AC")
-        .ambig(b'B', b"CGT")
-        .ambig(b'D', b"AGT")
-        .ambig(b'H', b"ACT")
-        .ambig(b'V', b"ACG")
-        .build_64(primer_seq)
+/// Search pattern of a primer. Primers shared between pairs have the same key,
+/// so that they are only searched once per strand.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PrimerKey {
+    pattern: Vec<u8>,
+    /// Maximum edit distance for fuzzy search, `None` for exact search.
+    max_dist: Option<u8>,
+    is_reverse: bool,
 }
 
-#[allow(unused)]
-pub fn amplicon_fuzzy_search<'a>(
-    seq: &'a [u8],
-    primer_pair: &PrimerPair,
-) -> Vec<AmpliconResult<'a>> {
-    let PrimerPair {
-        primer_name,
-        forward_primer,
-        reverse_primer,
-        min_len,
-        max_len,
-        num_mismatch,
-    } = primer_pair;
-
-    let num_mismatch = match num_mismatch {
-        Some(num_mismatch) => *num_mismatch as u8,
-        None => 1_u8,
+fn primer_keys(primer_pair: &PrimerPair, search_type: &SearchType) -> (PrimerKey, PrimerKey) {
+    let max_dist = |mismatch: Option<usize>| match search_type {
+        SearchType::Exact => None,
+        SearchType::Fuzzy => Some(mismatch.unwrap_or(1) as u8),
     };
 
-    let forward_len = forward_primer.len();
-    let reverse_len: usize = reverse_primer.len();
+    let forward = PrimerKey {
+        pattern: primer_pair.forward_primer.clone(),
+        max_dist: max_dist(primer_pair.forward_mismatch),
+        is_reverse: false,
+    };
 
     // For reverse primer, we need to 3' -> 5' direction.
-    let reverse_complement_primer = reverse_complement(reverse_primer);
-    let rcps = reverse_complement_primer.as_slice();
+    let reverse = PrimerKey {
+        pattern: reverse_complement(&primer_pair.reverse_primer),
+        max_dist: max_dist(primer_pair.reverse_mismatch),
+        is_reverse: true,
+    };
+
+    (forward, reverse)
+}
+
+/// All hits of a primer in `seq`, sorted by start position.
+fn find_hits(seq: &[u8], key: &PrimerKey) -> Vec<PrimerHit> {
+    match key.max_dist {
+        None => find_all(seq, &key.pattern)
+            .into_iter()
+            .map(|start| PrimerHit {
+                start,
+                end: start + key.pattern.len(),
+                mismatches: 0,
+                three_prime: Vec::new(),
+            })
+            .collect(),
+        Some(max_dist) => fuzzy_hits(
+            &mut myers_builder(&key.pattern),
+            seq,
+            key.pattern.len(),
+            max_dist,
+            key.is_reverse,
+        ),
+    }
+}
 
+/// Pairs forward and reverse primer hits with an insert length within the allowed range.
+/// Since hits are sorted, the reverse hits in the insert window of each forward hit are
+/// found with a binary search instead of comparing against every reverse hit.
+fn pair_hits<'a>(
+    seq: &'a [u8],
+    primer_pair: &PrimerPair,
+    forward_hits: &[PrimerHit],
+    reverse_hits: &[PrimerHit],
+) -> Vec<AmpliconResult<'a>> {
     let mut amplicons: Vec<AmpliconResult> = Vec::new();
 
-    let mut myers_forward = myers_builder(forward_primer);
-    let mut myers_reverse = myers_builder(rcps);
+    for forward_hit in forward_hits {
+        let start = forward_hit.end;
+        let window_start = start.saturating_add(primer_pair.min_len);
+        let window_end = start.saturating_add(primer_pair.max_len);
+
+        let first = reverse_hits.partition_point(|hit| hit.start < window_start);
+
+        for reverse_hit in reverse_hits[first..]
+            .iter()
+            .take_while(|hit| hit.start <= window_end)
+        {
+            let insert_length = reverse_hit.start - start;
+
+            amplicons.push(AmpliconResult {
+                amplicon: &seq[start..reverse_hit.start],
+                start,
+                end: reverse_hit.start,
+                forward_start: forward_hit.start,
+                reverse_end: reverse_hit.end,
+                insert_length,
+                total_length: reverse_hit.end - forward_hit.start,
+                mismatches: PrimerMismatches {
+                    forward: forward_hit.mismatches,
+                    reverse: reverse_hit.mismatches,
+                    forward_3p: forward_hit.three_prime.clone(),
+                    reverse_3p: reverse_hit.three_prime.clone(),
+                },
+            });
+        }
+    }
 
-    // There is something about myers matching that causes duplicates, probably
-    // multiple alignments in the same location. To prevent this, a temp solution
-    // is to keep track of and avoid duplicate starting positions.
-    //
-    // We only allow unique forward primer starting positions.
-    let mut forward_starts: HashSet<usize> = HashSet::new();
+    amplicons
+}
 
-    for (forward_hit, _, _) in myers_forward.find_all(seq, num_mismatch) {
-        let start = forward_hit + forward_len;
+/// Converts a hit on `strand_seq` to forward strand coordinates. With `include_primers`,
+/// the reported amplicon spans the primer binding sites.
+fn stranded_amplicon(
+    strand_seq: &[u8],
+    strand: Strand,
+    hit: &AmpliconResult,
+    include_primers: bool,
+) -> StrandedAmplicon {
+    let (start, end) = match include_primers {
+        true => (hit.forward_start, hit.reverse_end),
+        false => (hit.start, hit.end),
+    };
 
-        if forward_starts.contains(&start) {
-            continue;
-        }
-        forward_starts.insert(start);
+    let (forward_start, forward_end) = match strand {
+        Strand::Forward => (start, end),
+        Strand::Reverse => (strand_seq.len() - end, strand_seq.len() - start),
+    };
 
-        let mut reverse_starts: HashSet<usize> = HashSet::new();
-        for (reverse_hit, _, _) in myers_reverse.find_all(seq, num_mismatch) {
-            let insert_length = match reverse_hit.saturating_sub(start) {
-                0 => continue,
-                insert_length => insert_length,
-            };
+    StrandedAmplicon {
+        amplicon: strand_seq[start..end].to_vec(),
+        start: forward_start,
+        end: forward_end,
+        strand,
+        insert_length: hit.insert_length,
+        total_length: hit.total_length,
+        mismatches: hit.mismatches.clone(),
+    }
+}
 
-            if reverse_starts.contains(&reverse_hit) {
-                continue;
-            }
-            reverse_starts.insert(reverse_hit);
+/// Amplicons of all primer pairs on `seq` and, unless `forward_only`, on its reverse
+/// complement, as `(primer pair index, amplicon)`. Each distinct primer is searched once
+/// per strand and the hits are shared between primer pairs.
+pub fn search_both_strands(
+    seq: &[u8],
+    primer_pairs: &[PrimerPair],
+    search_type: &SearchType,
+    forward_only: bool,
+    include_primers: bool,
+) -> Vec<(usize, StrandedAmplicon)> {
+    let pair_keys: Vec<(PrimerKey, PrimerKey)> = primer_pairs
+        .iter()
+        .map(|primer_pair| primer_keys(primer_pair, search_type))
+        .collect();
 
-            // If amplicon is within allowed length.
-            if insert_length >= *min_len && insert_length <= *max_len {
-                let amplicon = &seq[start..reverse_hit];
+    let unique_keys: Vec<&PrimerKey> = pair_keys
+        .iter()
+        .flat_map(|(forward, reverse)| [forward, reverse])
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
 
-                let amplicon_result = AmpliconResult {
-                    amplicon,
-                    start,
-                    end: reverse_hit,
-                    insert_length,
-                    total_length: forward_len + insert_length + reverse_len,
-                };
+    let rc_seq: Vec<u8> = match forward_only {
+        true => Vec::new(),
+        false => reverse_complement(seq),
+    };
 
-                amplicons.push(amplicon_result);
-            }
-        }
+    let mut strands: Vec<(Strand, &[u8])> = vec![(Strand::Forward, seq)];
+    if !forward_only {
+        strands.push((Strand::Reverse, &rc_seq));
     }
 
-    amplicons
-}
-
-#[allow(unused)]
-pub fn amplicon_exact_search<'a>(
-    seq: &'a [u8],
-    primer_pair: &PrimerPair,
-) -> Vec<AmpliconResult<'a>> {
-    let PrimerPair {
-        primer_name,
-        forward_primer,
-        reverse_primer,
-        min_len,
-        max_len,
-        num_mismatch,
-    } = primer_pair;
-
-    let forward_len = forward_primer.len();
-    let reverse_len: usize = reverse_primer.len();
+    let mut amplicons: Vec<(usize, StrandedAmplicon)> = strands
+        .into_iter()
+        .flat_map(|(strand, strand_seq)| {
+            let hits: HashMap<&PrimerKey, Vec<PrimerHit>> = unique_keys
+                .par_iter()
+                .map(|key| (*key, find_hits(strand_seq, key)))
+                .collect();
+
+            primer_pairs
+                .par_iter()
+                .zip(pair_keys.par_iter())
+                .enumerate()
+                .flat_map_iter(|(i, (primer_pair, (forward_key, reverse_key)))| {
+                    pair_hits(
+                        strand_seq,
+                        primer_pair,
+                        &hits[forward_key],
+                        &hits[reverse_key],
+                    )
+                    .into_iter()
+                    .map(|hit| {
+                        let amplicon = stranded_amplicon(strand_seq, strand, &hit, include_primers);
+                        (i, amplicon)
+                    })
+                    .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
 
-    // For reverse primer, we need to 3' -> 5' direction.
-    let reverse_complement_primer = reverse_complement(reverse_primer);
+    // Keep the primer file order, with forward strand hits first.
+    amplicons.sort_by_key(|(i, _)| *i);
 
-    // Find all occurrences of the forward and reverse primers in seq.
-    let forward_hits: Vec<usize> = memmem::find_iter(seq, forward_primer).collect();
-    let reverse_hits: Vec<usize> =
-        memmem::find_iter(seq, reverse_complement_primer.as_slice()).collect();
+    amplicons
+}
 
-    let mut amplicons: Vec<AmpliconResult> = Vec::new();
+/// Comma separated positions, or `.` if there are none.
+fn format_positions(positions: &[usize]) -> String {
+    match positions.is_empty() {
+        true => ".".to_string(),
+        false => positions
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+    }
+}
 
-    // This is not ideal if we expect many, many matches.
-    for forward_hit in &forward_hits {
-        let start = forward_hit + forward_len;
+fn write_amplicons(
+    hits: &[(String, &PrimerPair, StrandedAmplicon)],
+    output_format: &AmpliconFormat,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    if let AmpliconFormat::Fasta = output_format {
+        let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+        for (id, primer_pair, amplicon) in hits {
+            let name = format!(
+                "{}|{}|{}-{}|{}",
+                id,
+                primer_pair.primer_name,
+                amplicon.start,
+                amplicon.end,
+                amplicon.strand.symbol()
+            );
+            writer.write(&Header::parse(name.as_bytes()), &amplicon.amplicon, None)?;
+        }
 
-        for reverse_hit in &reverse_hits {
-            let insert_length: usize = reverse_hit.saturating_sub(start);
+        writer.finish()?;
+        return Ok(());
+    }
 
-            // If amplicon is within allowed length.
-            if insert_length >= *min_len && insert_length <= *max_len {
-                let amplicon = &seq[start..*reverse_hit];
+    let mut writer = get_writer(outfile, writer_opts)?;
 
-                let amplicon_result = AmpliconResult {
-                    amplicon,
-                    start,
-                    end: *reverse_hit,
-                    insert_length,
-                    total_length: forward_len + insert_length + reverse_len,
-                };
+    if let AmpliconFormat::Tsv = output_format {
+        writeln!(
+            writer,
+            "sequence_id\tprimer_name\tstart\tend\tstrand\tinsert_length\tactual_length\tforward_mismatches\treverse_mismatches\tforward_3p_mismatches\treverse_3p_mismatches\tamplicon"
+        )?;
+    }
 
-                amplicons.push(amplicon_result);
-            }
+    for (id, primer_pair, amplicon) in hits {
+        let mismatches = &amplicon.mismatches;
+
+        match output_format {
+            AmpliconFormat::Bed => writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                id,
+                amplicon.start,
+                amplicon.end,
+                primer_pair.primer_name,
+                mismatches.forward + mismatches.reverse,
+                amplicon.strand.symbol()
+            )?,
+            _ => writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                id,
+                primer_pair.primer_name,
+                amplicon.start,
+                amplicon.end,
+                amplicon.strand.symbol(),
+                amplicon.insert_length,
+                amplicon.total_length,
+                mismatches.forward,
+                mismatches.reverse,
+                format_positions(&mismatches.forward_3p),
+                format_positions(&mismatches.reverse_3p),
+                String::from_utf8_lossy(&amplicon.amplicon)
+            )?,
         }
     }
 
-    amplicons
+    writer.finish()?;
+
+    Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn fasta_amplicon(
     fasta: Option<PathBuf>,
-    primers: &PathBuf,
+    primers: &Path,
     search_type: &SearchType,
+    min_len: usize,
+    max_len: usize,
+    forward_only: bool,
+    include_primers: bool,
+    output_format: &AmpliconFormat,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
     // Read and parse primer file.
-    let primer_pairs = parse_primer_file(primers)?;
-
-    let reader = bio_fasta_reader(fasta)?;
-
-    let mut writer = get_bufwriter(outfile)?;
-
-    writer.write_all(
-        format!(
-            "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
-            "sequence_id",
-            "primer_name",
-            "start",
-            "end",
-            "insert_length",
-            "actual_length",
-            "amplicon"
-        )
-        .as_bytes(),
-    )?;
-
-    let search_function = match search_type {
-        SearchType::Exact => amplicon_exact_search,
-        SearchType::Fuzzy => amplicon_fuzzy_search,
-    };
-
-    let amplicon_results: Vec<Vec<String>> = reader
-        .records()
-        .par_bridge()
-        .filter_map(|record| {
-            if let Ok(record) = record {
-                let result_vec: Vec<String> = primer_pairs
-                    .iter()
-                    .flat_map(|primer_pair| {
-                        let amplicons = search_function(record.seq(), primer_pair);
-                        amplicons
-                            .into_iter()
-                            .map(|amplicon| {
-                                format!(
-                                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
-                                    record.id(),
-                                    primer_pair.primer_name,
-                                    amplicon.start,
-                                    amplicon.end,
-                                    amplicon.insert_length,
-                                    amplicon.total_length,
-                                    std::str::from_utf8(amplicon.amplicon).unwrap()
-                                )
-                            })
-                            .collect::<Vec<_>>()
-                    })
-                    .collect();
-
-                if result_vec.is_empty() {
-                    return None;
-                }
-                return Some(result_vec);
-            }
-
-            None
+    let primer_pairs = parse_primer_file(primers, min_len, max_len)?;
+
+    let records = read_records(fasta)?;
+
+    let primer_pairs = &primer_pairs;
+
+    let hits: Vec<(String, &PrimerPair, StrandedAmplicon)> = records
+        .par_iter()
+        .flat_map_iter(|record| {
+            let id = String::from_utf8_lossy(record.header().id).into_owned();
+
+            search_both_strands(
+                &record.seq,
+                primer_pairs,
+                search_type,
+                forward_only,
+                include_primers,
+            )
+            .into_iter()
+            .map(move |(i, amplicon)| (id.clone(), &primer_pairs[i], amplicon))
         })
         .collect();
 
-    amplicon_results.iter().flatten().for_each(|r| {
-        writer.write_all(r.as_bytes()).expect("Failed to write.");
-    });
-
-    writer.flush()?;
-
-    Ok(())
+    write_amplicons(&hits, output_format, outfile, writer_opts)
 }
 
 #[rstest]
 #[case(b"",
-    &PrimerPair { forward_primer: b"A".to_vec(), reverse_primer: b"A".to_vec(), primer_name: "some_primer".to_string(), min_len: 0, max_len: 10, num_mismatch: Some(0)},
+    &PrimerPair { forward_primer: b"A".to_vec(), reverse_primer: b"A".to_vec(), primer_name: "some_primer".to_string(), min_len: 0, max_len: 10, forward_mismatch: Some(0), reverse_mismatch: Some(0)},
     vec![])]
 #[case(b"ATCGTTTTTATCG",
-    &PrimerPair { forward_primer: b"ATCG".to_vec(), reverse_primer: b"CGAT".to_vec(), primer_name: "some_primer".to_string(), min_len: 5, max_len: 5, num_mismatch: Some(0)},
-    vec![AmpliconResult { amplicon: b"TTTTT", start: 4, end: 4 + 5, insert_length: 5, total_length: 4 + 5 + 4}])]
+    &PrimerPair { forward_primer: b"ATCG".to_vec(), reverse_primer: b"CGAT".to_vec(), primer_name: "some_primer".to_string(), min_len: 5, max_len: 5, forward_mismatch: Some(0), reverse_mismatch: Some(0)},
+    vec![AmpliconResult { amplicon: b"TTTTT", start: 4, end: 4 + 5, forward_start: 0, reverse_end: 13, insert_length: 5, total_length: 4 + 5 + 4, ..Default::default()}])]
 #[case(b"ATCGTTTTTATCGTTTTTATCG",
-    &PrimerPair { forward_primer: b"ATCG".to_vec(), reverse_primer: b"CGAT".to_vec(), primer_name: "some_primer".to_string(), min_len: 5, max_len: 5, num_mismatch: Some(0)},
-    vec![AmpliconResult { amplicon: b"TTTTT", start: 4, end: 4 + 5, insert_length: 5, total_length: 4 + 5 + 4},
-         AmpliconResult { amplicon: b"TTTTT", start: 13, end: 13 + 5, insert_length: 5, total_length: 4 + 5 + 4}
+    &PrimerPair { forward_primer: b"ATCG".to_vec(), reverse_primer: b"CGAT".to_vec(), primer_name: "some_primer".to_string(), min_len: 5, max_len: 5, forward_mismatch: Some(0), reverse_mismatch: Some(0)},
+    vec![AmpliconResult { amplicon: b"TTTTT", start: 4, end: 4 + 5, forward_start: 0, reverse_end: 13, insert_length: 5, total_length: 4 + 5 + 4, ..Default::default()},
+         AmpliconResult { amplicon: b"TTTTT", start: 13, end: 13 + 5, forward_start: 9, reverse_end: 22, insert_length: 5, total_length: 4 + 5 + 4, ..Default::default()}
     ])]
+// Degenerate primers.
+#[case(b"ATCGTTTTTATCG",
+    &PrimerPair { forward_primer: b"AYCG".to_vec(), reverse_primer: b"CGNT".to_vec(), primer_name: "some_primer".to_string(), min_len: 5, max_len: 5, forward_mismatch: Some(0), reverse_mismatch: Some(0)},
+    vec![AmpliconResult { amplicon: b"TTTTT", start: 4, end: 4 + 5, forward_start: 0, reverse_end: 13, insert_length: 5, total_length: 4 + 5 + 4, ..Default::default()}])]
 
 fn test_amplicon_exact_match(
     #[case] seq: &[u8],
     #[case] primer_pair: &PrimerPair,
     #[case] expected: Vec<AmpliconResult>,
 ) {
-    assert_eq!(amplicon_exact_search(seq, primer_pair), expected)
+    let (forward_key, reverse_key) = primer_keys(primer_pair, &SearchType::Exact);
+    let amplicons = pair_hits(
+        seq,
+        primer_pair,
+        &find_hits(seq, &forward_key),
+        &find_hits(seq, &reverse_key),
+    );
+
+    assert_eq!(amplicons, expected)
+}
+
+#[rstest]
+// Forward primer ATCG, reverse complement of reverse primer AACC.
+#[case(b"ATCGTTTTTAACC", false, false, vec![
+    StrandedAmplicon { amplicon: b"TTTTT".to_vec(), start: 4, end: 9, strand: Strand::Forward, insert_length: 5, total_length: 13, mismatches: PrimerMismatches::default() },
+])]
+#[case(b"GGTTAAAAACGAT", false, false, vec![
+    StrandedAmplicon { amplicon: b"TTTTT".to_vec(), start: 4, end: 9, strand: Strand::Reverse, insert_length: 5, total_length: 13, mismatches: PrimerMismatches::default() },
+])]
+#[case(b"GGTTAAAAACGAT", false, true, vec![
+    StrandedAmplicon { amplicon: b"ATCGTTTTTAACC".to_vec(), start: 0, end: 13, strand: Strand::Reverse, insert_length: 5, total_length: 13, mismatches: PrimerMismatches::default() },
+])]
+#[case(b"GGTTAAAAACGAT", true, false, vec![])]
+
+fn test_search_both_strands(
+    #[case] seq: &[u8],
+    #[case] forward_only: bool,
+    #[case] include_primers: bool,
+    #[case] expected: Vec<StrandedAmplicon>,
+) {
+    let primer_pair = PrimerPair {
+        primer_name: "p".to_string(),
+        forward_primer: b"ATCG".to_vec(),
+        reverse_primer: b"GGTT".to_vec(),
+        min_len: 0,
+        max_len: 10,
+        forward_mismatch: Some(0),
+        reverse_mismatch: Some(0),
+    };
+
+    let amplicons: Vec<StrandedAmplicon> = search_both_strands(
+        seq,
+        &[primer_pair],
+        &SearchType::Exact,
+        forward_only,
+        include_primers,
+    )
+    .into_iter()
+    .map(|(_, amplicon)| amplicon)
+    .collect();
+
+    assert_eq!(amplicons, expected);
+}
+
+#[rstest]
+// Substitution at the 3' terminal base of the forward primer.
+#[case(b"ATCCTTTTTAACC", 1, 0, vec![1], vec![])]
+// Substitution at the 5' base of the forward primer, and the 2nd base from the 3' end of the reverse primer.
+#[case(b"TTCGTTTTTACCC", 1, 1, vec![4], vec![2])]
+
+fn test_amplicon_fuzzy_mismatches(
+    #[case] seq: &[u8],
+    #[case] expected_forward: usize,
+    #[case] expected_reverse: usize,
+    #[case] expected_forward_3p: Vec<usize>,
+    #[case] expected_reverse_3p: Vec<usize>,
+) {
+    let primer_pair = PrimerPair {
+        primer_name: "p".to_string(),
+        forward_primer: b"ATCG".to_vec(),
+        reverse_primer: b"GGTT".to_vec(),
+        min_len: 5,
+        max_len: 5,
+        forward_mismatch: Some(1),
+        reverse_mismatch: Some(1),
+    };
+
+    let (forward_key, reverse_key) = primer_keys(&primer_pair, &SearchType::Fuzzy);
+    let amplicons = pair_hits(
+        seq,
+        &primer_pair,
+        &find_hits(seq, &forward_key),
+        &find_hits(seq, &reverse_key),
+    );
+
+    assert_eq!(amplicons.len(), 1);
+    assert_eq!(
+        amplicons[0].mismatches,
+        PrimerMismatches {
+            forward: expected_forward,
+            reverse: expected_reverse,
+            forward_3p: expected_forward_3p,
+            reverse_3p: expected_reverse_3p,
+        }
+    );
+}
+
+#[rstest]
+// Exact site within a longer window, where Myers also reports hits with a deleted or
+// inserted base at either end.
+#[case(b"GGGGATCGTTTTT", b"ATCG", vec![(4, 8, 0)])]
+// Site with a substitution.
+#[case(b"GGGGATGGTTTTT", b"ATCG", vec![(4, 8, 1)])]
+// Site with a deleted base, shorter than the primer.
+#[case(b"CCCCACGACGTCCCC", b"ACGTACGT", vec![(4, 11, 1)])]
+// Two sites further apart than the maximum distance.
+#[case(b"ATCGGATCG", b"ATCG", vec![(0, 4, 0), (5, 9, 0)])]
+
+fn test_fuzzy_hits(
+    #[case] seq: &[u8],
+    #[case] primer: &[u8],
+    #[case] expected: Vec<(usize, usize, usize)>,
+) {
+    let hits: Vec<(usize, usize, usize)> =
+        fuzzy_hits(&mut myers_builder(primer), seq, primer.len(), 1, false)
+            .into_iter()
+            .map(|hit| (hit.start, hit.end, hit.mismatches))
+            .collect();
+
+    assert_eq!(hits, expected);
+}
+
+#[rstest]
+
+fn test_amplicon_fuzzy_indel() {
+    // Forward primer site with a deleted base, followed by the insert and the reverse
+    // complement of the reverse primer.
+    let seq = b"CCACGACGTTTTTTGGAACC";
+
+    let primer_pair = PrimerPair {
+        primer_name: "p".to_string(),
+        forward_primer: b"ACGTACGT".to_vec(),
+        reverse_primer: b"GGTTCC".to_vec(),
+        min_len: 0,
+        max_len: 10,
+        forward_mismatch: Some(1),
+        reverse_mismatch: Some(1),
+    };
+
+    let (forward_key, reverse_key) = primer_keys(&primer_pair, &SearchType::Fuzzy);
+    let amplicons = pair_hits(
+        seq,
+        &primer_pair,
+        &find_hits(seq, &forward_key),
+        &find_hits(seq, &reverse_key),
+    );
+
+    assert_eq!(amplicons.len(), 1);
+    assert_eq!(amplicons[0].amplicon, b"TTTTT");
+    assert_eq!(
+        (amplicons[0].forward_start, amplicons[0].reverse_end),
+        (2, 20)
+    );
+    assert_eq!(amplicons[0].total_length, 18);
 }
@@ -1,3 +1,5 @@
 #[allow(clippy::module_inception)]
 pub mod amplicon;
 pub use amplicon::fasta_amplicon;
+
+pub mod primers;
@@ -0,0 +1,374 @@
+use crate::errors::AppError;
+use crate::iupac::is_nucleotide;
+use rstest::*;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+#[derive(Debug, PartialEq)]
+pub struct PrimerPair {
+    pub primer_name: String,
+    pub forward_primer: Vec<u8>,
+    pub reverse_primer: Vec<u8>,
+    pub min_len: usize,
+    pub max_len: usize,
+    pub forward_mismatch: Option<usize>,
+    pub reverse_mismatch: Option<usize>,
+}
+
+/// Single primer from a FASTA or BED primer file, before pairing.
+struct Primer {
+    name: String,
+    is_forward: bool,
+    seq: Vec<u8>,
+    line_number: usize,
+}
+
+#[derive(Default)]
+struct PrimerGroup {
+    forward: Vec<Vec<u8>>,
+    reverse: Vec<Vec<u8>>,
+    line_number: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum PrimerFormat {
+    Tsv,
+    Fasta,
+    Bed,
+}
+
+fn line_error(line_number: usize, reason: &str) -> AppError {
+    AppError::PrimerLineFormatError(format!("line {line_number}: {reason}"))
+}
+
+fn parse_primer_seq(seq: &str, line_number: usize) -> Result<Vec<u8>, AppError> {
+    if seq.is_empty() || !seq.bytes().all(is_nucleotide) {
+        return Err(line_error(
+            line_number,
+            &format!("invalid primer sequence '{seq}'"),
+        ));
+    }
+
+    // RNA primers are searched as DNA.
+    Ok(seq.to_ascii_uppercase().replace('U', "T").into_bytes())
+}
+
+fn parse_number(value: &str, line_number: usize) -> Result<usize, AppError> {
+    value
+        .parse::<usize>()
+        .map_err(|_| line_error(line_number, &format!("invalid number '{value}'")))
+}
+
+/// Lines with their (1-based) line number, without blank lines, comments and BED track lines.
+fn data_lines(content: &str) -> Vec<(usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end()))
+        .filter(|(_, line)| {
+            !line.trim().is_empty()
+                && !line.starts_with('#')
+                && !line.starts_with("track")
+                && !line.starts_with("browser")
+        })
+        .collect()
+}
+
+/// FASTA if the first line is a header, BED if the second and third columns are
+/// coordinates, otherwise TSV.
+fn detect_format(lines: &[(usize, &str)]) -> PrimerFormat {
+    let Some((_, first)) = lines.first() else {
+        return PrimerFormat::Tsv;
+    };
+
+    if first.starts_with('>') {
+        return PrimerFormat::Fasta;
+    }
+
+    let fields: Vec<&str> = first.split('\t').map(str::trim).collect();
+    let is_coordinate = |i: usize| fields.get(i).is_some_and(|f| f.parse::<usize>().is_ok());
+
+    match fields.len() >= 6 && is_coordinate(1) && is_coordinate(2) {
+        true => PrimerFormat::Bed,
+        false => PrimerFormat::Tsv,
+    }
+}
+
+/// `name  forward  reverse  min_len  max_len  [mismatches | forward_mismatches  reverse_mismatches]`
+fn parse_tsv(lines: &[(usize, &str)]) -> Result<Vec<PrimerPair>, AppError> {
+    let mut primer_pairs: Vec<PrimerPair> = Vec::new();
+
+    for (i, (line_number, line)) in lines.iter().enumerate() {
+        let line_number = *line_number;
+        let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+
+        if !(5..=7).contains(&fields.len()) {
+            return Err(line_error(
+                line_number,
+                &format!("expected 5 to 7 columns, found {}", fields.len()),
+            ));
+        }
+
+        // Header line.
+        if i == 0 && fields[3].parse::<usize>().is_err() {
+            continue;
+        }
+
+        let mismatches = fields[5..]
+            .iter()
+            .map(|f| parse_number(f, line_number))
+            .collect::<Result<Vec<usize>, _>>()?;
+
+        primer_pairs.push(PrimerPair {
+            primer_name: fields[0].to_string(),
+            forward_primer: parse_primer_seq(fields[1], line_number)?,
+            reverse_primer: parse_primer_seq(fields[2], line_number)?,
+            min_len: parse_number(fields[3], line_number)?,
+            max_len: parse_number(fields[4], line_number)?,
+            forward_mismatch: mismatches.first().copied(),
+            reverse_mismatch: mismatches.last().copied(),
+        });
+    }
+
+    Ok(primer_pairs)
+}
+
+/// Pairs forward and reverse primers by amplicon name, keeping the order in
+/// which amplicons first appear. Alternative primers result in one pair per combination.
+fn pair_primers(
+    primers: Vec<Primer>,
+    min_len: usize,
+    max_len: usize,
+) -> Result<Vec<PrimerPair>, AppError> {
+    let mut order: Vec<String> = Vec::new();
+    let mut grouped: HashMap<String, PrimerGroup> = HashMap::new();
+
+    for primer in primers {
+        let group = grouped.entry(primer.name.clone()).or_insert_with(|| {
+            order.push(primer.name);
+            PrimerGroup {
+                line_number: primer.line_number,
+                ..Default::default()
+            }
+        });
+
+        match primer.is_forward {
+            true => group.forward.push(primer.seq),
+            false => group.reverse.push(primer.seq),
+        }
+    }
+
+    let mut primer_pairs: Vec<PrimerPair> = Vec::new();
+
+    for name in order {
+        let PrimerGroup {
+            forward,
+            reverse,
+            line_number,
+        } = &grouped[&name];
+
+        if forward.is_empty() || reverse.is_empty() {
+            return Err(line_error(
+                *line_number,
+                &format!("primer {name} is missing its forward or reverse primer"),
+            ));
+        }
+
+        for forward_primer in forward {
+            for reverse_primer in reverse {
+                primer_pairs.push(PrimerPair {
+                    primer_name: name.clone(),
+                    forward_primer: forward_primer.clone(),
+                    reverse_primer: reverse_primer.clone(),
+                    min_len,
+                    max_len,
+                    forward_mismatch: None,
+                    reverse_mismatch: None,
+                });
+            }
+        }
+    }
+
+    Ok(primer_pairs)
+}
+
+/// Primer FASTA, where `name_F` and `name_R` form a pair.
+fn parse_fasta(
+    lines: &[(usize, &str)],
+    min_len: usize,
+    max_len: usize,
+) -> Result<Vec<PrimerPair>, AppError> {
+    let mut primers: Vec<Primer> = Vec::new();
+
+    for (line_number, line) in lines {
+        let line_number = *line_number;
+
+        let Some(header) = line.strip_prefix('>') else {
+            let primer = primers
+                .last_mut()
+                .ok_or(line_error(line_number, "sequence before first header"))?;
+            primer
+                .seq
+                .extend(parse_primer_seq(line.trim(), line_number)?);
+            continue;
+        };
+
+        let id = header.split_whitespace().next().unwrap_or_default();
+
+        let (name, is_forward) = if let Some(name) = id.strip_suffix("_F") {
+            (name, true)
+        } else if let Some(name) = id.strip_suffix("_R") {
+            (name, false)
+        } else {
+            return Err(line_error(
+                line_number,
+                &format!("primer name '{id}' does not end with _F or _R"),
+            ));
+        };
+
+        primers.push(Primer {
+            name: name.to_string(),
+            is_forward,
+            seq: Vec::new(),
+            line_number,
+        });
+    }
+
+    if let Some(primer) = primers.iter().find(|p| p.seq.is_empty()) {
+        return Err(line_error(
+            primer.line_number,
+            &format!("primer {} has no sequence", primer.name),
+        ));
+    }
+
+    pair_primers(primers, min_len, max_len)
+}
+
+/// ARTIC style primer scheme: `chrom  start  end  name  pool  strand  sequence`, with
+/// names such as `scheme_1_LEFT` or `scheme_1_RIGHT_alt1`.
+fn parse_bed(
+    lines: &[(usize, &str)],
+    min_len: usize,
+    max_len: usize,
+) -> Result<Vec<PrimerPair>, AppError> {
+    let mut primers: Vec<Primer> = Vec::new();
+
+    for (line_number, line) in lines {
+        let line_number = *line_number;
+        let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+
+        if fields.len() < 7 {
+            return Err(line_error(
+                line_number,
+                "expected 7 columns (chrom, start, end, name, pool, strand, sequence)",
+            ));
+        }
+
+        let name = fields[3];
+        let (amplicon_name, is_forward) = if let Some(i) = name.rfind("_LEFT") {
+            (&name[..i], true)
+        } else if let Some(i) = name.rfind("_RIGHT") {
+            (&name[..i], false)
+        } else {
+            return Err(line_error(
+                line_number,
+                &format!("primer name '{name}' does not contain _LEFT or _RIGHT"),
+            ));
+        };
+
+        primers.push(Primer {
+            name: amplicon_name.to_string(),
+            is_forward,
+            seq: parse_primer_seq(fields[6], line_number)?,
+            line_number,
+        });
+    }
+
+    pair_primers(primers, min_len, max_len)
+}
+
+fn parse_primers(
+    content: &str,
+    min_len: usize,
+    max_len: usize,
+) -> Result<Vec<PrimerPair>, AppError> {
+    let lines = data_lines(content);
+
+    let primer_pairs = match detect_format(&lines) {
+        PrimerFormat::Tsv => parse_tsv(&lines)?,
+        PrimerFormat::Fasta => parse_fasta(&lines, min_len, max_len)?,
+        PrimerFormat::Bed => parse_bed(&lines, min_len, max_len)?,
+    };
+
+    match primer_pairs.len() {
+        0 => Err(AppError::NoPrimersFoundError),
+        _ => Ok(primer_pairs),
+    }
+}
+
+/// Reads primer pairs from a primer TSV, primer FASTA or ARTIC primer BED. Insert
+/// lengths for FASTA and BED primers are given by `min_len` and `max_len`.
+pub fn parse_primer_file(
+    primer_file: &Path,
+    min_len: usize,
+    max_len: usize,
+) -> Result<Vec<PrimerPair>, AppError> {
+    parse_primers(&read_to_string(primer_file)?, min_len, max_len)
+}
+
+#[rstest]
+#[case("p1\tATCG\tcgat\t10\t20\n", vec![
+    PrimerPair { primer_name: "p1".into(), forward_primer: b"ATCG".to_vec(), reverse_primer: b"CGAT".to_vec(), min_len: 10, max_len: 20, forward_mismatch: None, reverse_mismatch: None },
+])]
+#[case("# comment\nname\tforward\treverse\tmin\tmax\np1\tATCG\tCGAT\t10\t20\t2\n", vec![
+    PrimerPair { primer_name: "p1".into(), forward_primer: b"ATCG".to_vec(), reverse_primer: b"CGAT".to_vec(), min_len: 10, max_len: 20, forward_mismatch: Some(2), reverse_mismatch: Some(2) },
+])]
+#[case("p1\tAUCG\tcgau\t10\t20\n", vec![
+    PrimerPair { primer_name: "p1".into(), forward_primer: b"ATCG".to_vec(), reverse_primer: b"CGAT".to_vec(), min_len: 10, max_len: 20, forward_mismatch: None, reverse_mismatch: None },
+])]
+#[case("p1\tATCG\tCGAT\t10\t20\t1\t3\n", vec![
+    PrimerPair { primer_name: "p1".into(), forward_primer: b"ATCG".to_vec(), reverse_primer: b"CGAT".to_vec(), min_len: 10, max_len: 20, forward_mismatch: Some(1), reverse_mismatch: Some(3) },
+])]
+#[case(">p1_F\nATCG\n>p2_F\nAAAA\n>p1_R desc\nCG\nAT\n>p2_R\nTTTT\n", vec![
+    PrimerPair { primer_name: "p1".into(), forward_primer: b"ATCG".to_vec(), reverse_primer: b"CGAT".to_vec(), min_len: 0, max_len: 100, forward_mismatch: None, reverse_mismatch: None },
+    PrimerPair { primer_name: "p2".into(), forward_primer: b"AAAA".to_vec(), reverse_primer: b"TTTT".to_vec(), min_len: 0, max_len: 100, forward_mismatch: None, reverse_mismatch: None },
+])]
+#[case("MN908947.3\t30\t54\tnCoV_1_LEFT\t1\t+\tACCA\nMN908947.3\t385\t410\tnCoV_1_RIGHT\t1\t-\tCATC\nMN908947.3\t390\t412\tnCoV_1_RIGHT_alt1\t1\t-\tGGTT\n", vec![
+    PrimerPair { primer_name: "nCoV_1".into(), forward_primer: b"ACCA".to_vec(), reverse_primer: b"CATC".to_vec(), min_len: 0, max_len: 100, forward_mismatch: None, reverse_mismatch: None },
+    PrimerPair { primer_name: "nCoV_1".into(), forward_primer: b"ACCA".to_vec(), reverse_primer: b"GGTT".to_vec(), min_len: 0, max_len: 100, forward_mismatch: None, reverse_mismatch: None },
+])]
+
+fn test_parse_primers(#[case] content: &str, #[case] expected: Vec<PrimerPair>) {
+    assert_eq!(parse_primers(content, 0, 100).unwrap(), expected);
+}
+
+#[rstest]
+#[case("p1\tATCG\tCGAT\t10\n", "line 1: expected 5 to 7 columns, found 4")]
+#[case(
+    "p1\tATCG\tCGAT\t10\t20\np2\tATCG\tCG-T\t10\t20\n",
+    "line 2: invalid primer sequence 'CG-T'"
+)]
+#[case(
+    "p1\tATCG\tCGAT\t10\t20\n\np2\tATCG\tCGAT\t10\tx\n",
+    "line 3: invalid number 'x'"
+)]
+#[case(
+    ">p1_F\nATCG\n>p1\nCGAT\n",
+    "line 3: primer name 'p1' does not end with _F or _R"
+)]
+#[case(
+    ">p1_F\nATCG\n",
+    "line 1: primer p1 is missing its forward or reverse primer"
+)]
+#[case(
+    "chr\t1\t10\tp_1_LEFT\t1\t+\n",
+    "line 1: expected 7 columns (chrom, start, end, name, pool, strand, sequence)"
+)]
+
+fn test_parse_primers_error(#[case] content: &str, #[case] expected: &str) {
+    match parse_primers(content, 0, 100) {
+        Err(AppError::PrimerLineFormatError(e)) => assert_eq!(e, expected),
+        other => panic!("unexpected result {other:?}"),
+    }
+}
@@ -8,6 +8,70 @@ pub enum SearchType {
     Fuzzy,
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+pub enum AmpliconFormat {
+    Tsv,
+    Fasta,
+    Bed,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum HomopolymerFormat {
+    Tsv,
+    Bed,
+    Summary,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum LiftTarget {
+    Original,
+    Compressed,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum DistFormat {
+    Tsv,
+    Phylip,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum WindowFormat {
+    Tsv,
+    Bedgraph,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum WindowMetric {
+    Gc,
+    Entropy,
+    Softmask,
+    Ambiguous,
+    GcSkew,
+    CumulativeGcSkew,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum ReverseMode {
+    Reverse,
+    Complement,
+    ReverseComplement,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum MaskMode {
+    Hard,
+    Soft,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompressType {
+    Gzip,
+    Bgzip,
+    Zstd,
+    Xz,
+    None,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum SortType {
     Length,
@@ -37,6 +101,29 @@ pub struct GlobalOpts {
         help = "Not applicable to all subcommands. By default set to 0, meaning Rayon will choose automatically."
     )]
     pub threads: usize,
+
+    #[clap(
+        long,
+        global = true,
+        default_value_t = 0,
+        help = "Line width of FASTA output. By default set to 0, meaning sequences are not wrapped."
+    )]
+    pub line_width: usize,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Only write sequence ids to FASTA headers, stripping descriptions."
+    )]
+    pub id_only: bool,
+
+    #[clap(
+        value_enum,
+        long,
+        global = true,
+        help = "Output compression. By default inferred from the output file extension (.gz, .bgz, .zst, .xz)."
+    )]
+    pub compress: Option<CompressType>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -72,6 +159,31 @@ pub enum SubCommand {
         #[clap(short, long)]
         strict: bool,
 
+        #[clap(value_enum, long, default_value_t = HomopolymerFormat::Tsv)]
+        output_format: HomopolymerFormat,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Repeats {
+        #[clap(short, long)]
+        fasta: Option<PathBuf>,
+
+        #[clap(long, default_value_t = 1)]
+        min_period: usize,
+
+        #[clap(long, default_value_t = 6)]
+        max_period: usize,
+
+        #[clap(short = 'c', long, value_delimiter = ',', default_values_t = [10, 6, 5, 5, 5, 5])]
+        min_copies: Vec<usize>,
+
+        #[clap(short, long)]
+        imperfect: bool,
+
+        #[clap(long, default_value_t = 0.9)]
+        min_purity: f64,
+
         #[clap(short, long)]
         outfile: Option<PathBuf>,
     },
@@ -114,6 +226,13 @@ pub enum SubCommand {
         #[clap(long, default_value_t = 100.0)]
         max_entropy: f32,
 
+        // Quality filters, only applicable to FASTQ.
+        #[clap(long, default_value_t = 0.0)]
+        min_qual: f64,
+
+        #[clap(long)]
+        max_ee: Option<f64>,
+
         #[clap(short, long)]
         outfile: Option<PathBuf>,
     },
@@ -130,6 +249,22 @@ pub enum SubCommand {
         #[clap(short, long)]
         outfile: Option<PathBuf>,
     },
+    Sliding {
+        #[clap(short, long)]
+        fasta: Option<PathBuf>,
+
+        #[clap(short, long, default_value_t = 100)]
+        window: usize,
+
+        #[clap(short, long)]
+        step: Option<usize>,
+
+        #[clap(short, long)]
+        circular: bool,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
     Sample {
         #[clap(short, long)]
         fasta: Option<PathBuf>,
@@ -167,12 +302,15 @@ pub enum SubCommand {
         #[clap(short, long, default_value_t = 5)]
         num_seqs: usize,
 
+        #[clap(short, long, default_value_t = 0)]
+        skip: usize,
+
         #[clap(short, long)]
         outfile: Option<PathBuf>,
     },
     Grep {
         #[clap(short, long)]
-        fastq: Option<PathBuf>,
+        fasta: Option<PathBuf>,
 
         #[clap(short, long)]
         pattern: String,
@@ -190,6 +328,102 @@ pub enum SubCommand {
         #[clap(short, long)]
         search_type: SearchType,
 
+        #[clap(long, default_value_t = 0)]
+        min_len: usize,
+
+        #[clap(long, default_value_t = 2000)]
+        max_len: usize,
+
+        #[clap(long)]
+        forward_only: bool,
+
+        #[clap(long)]
+        include_primers: bool,
+
+        #[clap(value_enum, long, default_value_t = AmpliconFormat::Tsv)]
+        output_format: AmpliconFormat,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Expand {
+        #[clap(short, long)]
+        fasta: Option<PathBuf>,
+
+        #[clap(short, long, default_value_t = 1000)]
+        max_expansions: usize,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Primers {
+        #[clap(subcommand)]
+        command: PrimersCommand,
+    },
+    Kmers {
+        #[clap(short, long)]
+        fasta: Option<PathBuf>,
+
+        #[clap(short, long, default_value_t = 21)]
+        kmer_size: usize,
+
+        #[clap(short, long)]
+        canonical: bool,
+
+        #[clap(short = 'n', long, default_value_t = 10)]
+        top: usize,
+
+        #[clap(short, long)]
+        profiles: Option<PathBuf>,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Sketch {
+        #[clap(short, long, num_args = 1.., required = true)]
+        fasta: Vec<PathBuf>,
+
+        #[clap(short, long, default_value_t = 21)]
+        kmer_size: usize,
+
+        #[clap(short, long, default_value_t = 1000)]
+        sketch_size: usize,
+
+        #[clap(long)]
+        scaled: Option<u64>,
+
+        #[clap(short = 'r', long)]
+        per_record: bool,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Dist {
+        #[clap(short, long, num_args = 1.., required = true)]
+        sketches: Vec<PathBuf>,
+
+        #[clap(value_enum, long, default_value_t = DistFormat::Tsv)]
+        output_format: DistFormat,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Window {
+        #[clap(short, long)]
+        fasta: Option<PathBuf>,
+
+        #[clap(short, long, default_value_t = 1000)]
+        window: usize,
+
+        #[clap(short, long)]
+        step: Option<usize>,
+
+        #[clap(value_enum, long, default_value_t = WindowFormat::Tsv)]
+        output_format: WindowFormat,
+
+        #[clap(value_enum, short, long, default_value_t = WindowMetric::Gc)]
+        metric: WindowMetric,
+
         #[clap(short, long)]
         outfile: Option<PathBuf>,
     },
@@ -200,6 +434,50 @@ pub enum SubCommand {
         #[clap(short, long, default_value_t = 5)]
         max_hp_len: usize,
 
+        #[clap(short, long)]
+        ignore_case: bool,
+
+        #[clap(long)]
+        skip_n: bool,
+
+        #[clap(long)]
+        max_dinuc_copies: Option<usize>,
+
+        #[clap(long)]
+        map: Option<PathBuf>,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Mask {
+        #[clap(short, long)]
+        fasta: Option<PathBuf>,
+
+        #[clap(short = 'T', long, default_value_t = 20)]
+        threshold: usize,
+
+        #[clap(short, long, default_value_t = 64)]
+        window: usize,
+
+        #[clap(value_enum, short, long, default_value_t = MaskMode::Soft)]
+        mask_mode: MaskMode,
+
+        #[clap(short, long)]
+        bed: Option<PathBuf>,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Lift {
+        #[clap(long)]
+        map: PathBuf,
+
+        #[clap(short, long)]
+        bed: Option<PathBuf>,
+
+        #[clap(value_enum, long, default_value_t = LiftTarget::Original)]
+        to: LiftTarget,
+
         #[clap(short, long)]
         outfile: Option<PathBuf>,
     },
@@ -207,6 +485,87 @@ pub enum SubCommand {
         #[clap(short, long)]
         fasta: Option<PathBuf>,
 
+        #[clap(value_enum, short, long, default_value_t = ReverseMode::ReverseComplement)]
+        mode: ReverseMode,
+
+        #[clap(long)]
+        tag: bool,
+
+        #[clap(short, long)]
+        ids: Option<PathBuf>,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Faidx {
+        #[clap(short, long)]
+        fasta: PathBuf,
+
+        #[clap(short, long)]
+        regions: Vec<String>,
+
+        #[clap(short, long)]
+        bed: Option<PathBuf>,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Fq2fa {
+        #[clap(short, long)]
+        fastq: Option<PathBuf>,
+
+        #[clap(short = 'q', long)]
+        mask_qual: Option<u8>,
+
+        #[clap(value_enum, short, long, default_value_t = MaskMode::Hard)]
+        mask_mode: MaskMode,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Fa2fq {
+        #[clap(short, long)]
+        fasta: Option<PathBuf>,
+
+        #[clap(short, long, default_value_t = 40)]
+        qual: u8,
+
+        #[clap(long)]
+        softmask_qual: Option<u8>,
+
+        #[clap(long)]
+        ambig_qual: Option<u8>,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Fa2twobit {
+        #[clap(short, long)]
+        fasta: Option<PathBuf>,
+
+        #[clap(short, long)]
+        outfile: PathBuf,
+    },
+    Twobit2fa {
+        #[clap(short = 'i', long)]
+        twobit: PathBuf,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+    Gb2fa {
+        #[clap(short, long)]
+        genbank: Option<PathBuf>,
+
+        #[clap(short, long, value_delimiter = ',')]
+        features: Vec<String>,
+
+        #[clap(short, long, value_delimiter = ',', default_values_t = ["locus_tag".to_string(), "product".to_string()])]
+        qualifiers: Vec<String>,
+
+        #[clap(long)]
+        features_only: bool,
+
         #[clap(short, long)]
         outfile: Option<PathBuf>,
     },
@@ -221,3 +580,29 @@ pub enum SubCommand {
         outdir: PathBuf,
     },
 }
+
+#[derive(Debug, Subcommand)]
+pub enum PrimersCommand {
+    Check {
+        #[clap(short, long)]
+        primers: PathBuf,
+
+        #[clap(short, long)]
+        background: Option<PathBuf>,
+
+        #[clap(value_enum, short, long, default_value_t = SearchType::Exact)]
+        search_type: SearchType,
+
+        #[clap(long, default_value_t = 0)]
+        min_len: usize,
+
+        #[clap(long, default_value_t = 2000)]
+        max_len: usize,
+
+        #[clap(long, default_value_t = 4)]
+        min_dimer_len: usize,
+
+        #[clap(short, long)]
+        outfile: Option<PathBuf>,
+    },
+}
@@ -1,11 +1,48 @@
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
+use crate::fastx::{FastxWriter, Header, IndexedFasta, WriterOpts, fastx_reader, format_extension};
+use needletail::parse_fastx_reader;
+use rayon::prelude::*;
+use std::path::Path;
 use std::{fs::create_dir_all, path::PathBuf};
 
+/// Writes chunks of an indexed (plain or BGZF) FASTA in parallel. Each chunk reads its
+/// records with its own file handle, starting at the offset of its first record.
+fn chunk_indexed(
+    index: &IndexedFasta,
+    num_contigs_per_file: usize,
+    outdir: &Path,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let num_chunks = index.records().len().div_ceil(num_contigs_per_file);
+
+    (0..num_chunks).into_par_iter().try_for_each(|chunk| {
+        let first = chunk * num_contigs_per_file;
+        let mut reader = parse_fastx_reader(index.reader_at(index.record_start(first))?)?;
+
+        let path = outdir.join(writer_opts.file_name(&format!("chunk_{}", chunk + 1), "fasta"));
+        let mut writer = FastxWriter::new(Some(path), writer_opts)?;
+
+        let mut count: usize = 0;
+        while count < num_contigs_per_file {
+            let record = match reader.next() {
+                Some(Ok(record)) => record,
+                Some(Err(_)) => continue,
+                None => break,
+            };
+
+            writer.write(&Header::parse(record.id()), &record.seq(), None)?;
+            count += 1;
+        }
+
+        writer.finish()
+    })
+}
+
 pub fn fasta_chunk(
     fasta: Option<PathBuf>,
     num_contigs_per_file: usize,
     outdir: &PathBuf,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
     if num_contigs_per_file == 0 {
         return Err(AppError::InvalidArgError(
@@ -13,12 +50,24 @@ pub fn fasta_chunk(
         ));
     }
 
-    let mut reader = needletail_reader(fasta)?;
     create_dir_all(outdir)?;
 
+    if let Some(path) = &fasta
+        && IndexedFasta::has_index(path)?
+    {
+        return chunk_indexed(
+            &IndexedFasta::open(path)?,
+            num_contigs_per_file,
+            outdir,
+            writer_opts,
+        );
+    }
+
+    let mut reader = fastx_reader(fasta)?;
+
     let mut count: usize = 0;
     let mut chunk: usize = 0;
-    let mut writer = None;
+    let mut writer: Option<FastxWriter> = None;
 
     while let Some(record_result) = reader.next() {
         let record = match record_result {
@@ -28,13 +77,26 @@ pub fn fasta_chunk(
 
         if count.is_multiple_of(num_contigs_per_file) {
             chunk += 1;
-            let path = outdir.join(format!("chunk_{chunk}.fasta"));
-            writer = Some(get_bufwriter(Some(path))?);
+            let path = outdir.join(
+                writer_opts.file_name(&format!("chunk_{chunk}"), format_extension(record.format())),
+            );
+            if let Some(mut previous) = writer.take() {
+                previous.finish()?;
+            }
+            writer = Some(FastxWriter::new(Some(path), writer_opts)?);
         }
 
-        record.write(writer.as_mut().expect("unexpected writer error"), None)?;
+        writer.as_mut().expect("unexpected writer error").write(
+            &Header::parse(record.id()),
+            &record.seq(),
+            record.qual(),
+        )?;
         count += 1;
     }
 
+    if let Some(mut writer) = writer {
+        writer.finish()?;
+    }
+
     Ok(())
 }
@@ -1,41 +1,150 @@
+use crate::compress::map::{blocks, write_blocks};
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader, get_writer};
 use rstest::*;
+use std::ops::Range;
+use std::path::PathBuf;
+
+#[derive(Debug, Default)]
+pub struct CompressOpts {
+    pub max_hp_len: usize,
+    /// Compare bases case-insensitively, kept bases keep their case.
+    pub ignore_case: bool,
+    /// Keep runs of N (e.g. gap placeholders) uncompressed.
+    pub skip_n: bool,
+    /// Also compress dinucleotide repeats down to this many copies.
+    pub max_dinuc_copies: Option<usize>,
+}
+
+impl CompressOpts {
+    #[inline]
+    fn same_base(&self, a: u8, b: u8) -> bool {
+        match self.ignore_case {
+            true => a.eq_ignore_ascii_case(&b),
+            false => a == b,
+        }
+    }
 
-use std::{io::Write, path::PathBuf};
+    #[inline]
+    fn is_skipped(&self, nt: u8) -> bool {
+        self.skip_n && matches!(nt, b'N' | b'n')
+    }
+}
 
-fn homopolymer_compression(seq: &[u8], max_hp_len: usize) -> Vec<u8> {
-    let mut hp_compressed: Vec<u8> = Vec::with_capacity(seq.len());
+/// Ranges of `seq` that are kept after homopolymer compression. Used for
+/// compressing both the sequence and (for FASTQ) the quality string.
+fn kept_ranges(seq: &[u8], opts: &CompressOpts) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
 
     let mut i: usize = 0;
 
     while i < seq.len() {
         let mut j = i + 1;
 
-        while j < seq.len() && seq[j] == seq[i] {
+        while j < seq.len() && opts.same_base(seq[j], seq[i]) {
             j += 1;
         }
 
-        for _ in 0..std::cmp::min(j - i, max_hp_len) {
-            hp_compressed.push(seq[i]);
+        if opts.is_skipped(seq[i]) {
+            ranges.push(i..j);
+            i = j;
+            continue;
+        }
+
+        // Homopolymers take precedence over dinucleotide repeats.
+        if let Some(max_copies) = opts.max_dinuc_copies
+            && j - i == 1
+            && j < seq.len()
+            && !opts.is_skipped(seq[j])
+        {
+            let mut k = i + 2;
+            while k < seq.len() && opts.same_base(seq[k], seq[k - 2]) {
+                k += 1;
+            }
+
+            let copies = (k - i) / 2;
+            if copies >= 2 {
+                ranges.push(i..i + 2 * copies.min(max_copies));
+                // A trailing partial copy starts the next run.
+                i += 2 * copies;
+                continue;
+            }
         }
+
+        ranges.push(i..i + std::cmp::min(j - i, opts.max_hp_len));
         i = j;
     }
 
-    hp_compressed
+    ranges
+}
+
+#[inline]
+fn gather(seq: &[u8], ranges: &[Range<usize>]) -> Vec<u8> {
+    let mut gathered: Vec<u8> = Vec::with_capacity(seq.len());
+
+    for range in ranges {
+        gathered.extend_from_slice(&seq[range.clone()]);
+    }
+
+    gathered
+}
+
+/// Compresses `seq` and, if provided, the corresponding quality string. Also returns
+/// the kept ranges, for mapping coordinates between the original and compressed sequence.
+fn homopolymer_compression(
+    seq: &[u8],
+    qual: Option<&[u8]>,
+    opts: &CompressOpts,
+) -> (Vec<u8>, Option<Vec<u8>>, Vec<Range<usize>>) {
+    let ranges = kept_ranges(seq, opts);
+
+    (
+        gather(seq, &ranges),
+        qual.map(|qual| gather(qual, &ranges)),
+        ranges,
+    )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn fasta_compress(
     fasta: Option<PathBuf>,
     max_hp_len: usize,
+    ignore_case: bool,
+    skip_n: bool,
+    max_dinuc_copies: Option<usize>,
+    map: Option<PathBuf>,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
-    assert!(max_hp_len > 0, "value of max_hp_len must be > 0.");
+    if max_hp_len == 0 {
+        return Err(AppError::InvalidArgError(
+            "max homopolymer length must be > 0".into(),
+        ));
+    }
+
+    if max_dinuc_copies == Some(0) {
+        return Err(AppError::InvalidArgError(
+            "max dinucleotide copies must be > 0".into(),
+        ));
+    }
 
-    let mut reader = needletail_reader(fasta)?;
+    let opts = CompressOpts {
+        max_hp_len,
+        ignore_case,
+        skip_n,
+        max_dinuc_copies,
+    };
+
+    let mut reader = fastx_reader(fasta)?;
 
     // Output file writer.
-    let mut writer = get_bufwriter(outfile)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    // Optional coordinate map writer.
+    let mut map_writer = match map {
+        Some(map) => Some(get_writer(Some(map), writer_opts)?),
+        None => None,
+    };
 
     while let Some(record) = reader.next() {
         let record = match record {
@@ -43,21 +152,24 @@ pub fn fasta_compress(
             Err(_) => continue,
         };
 
-        let compressed_sequence = homopolymer_compression(&record.seq(), max_hp_len);
+        let seq = record.seq();
+        let header = Header::parse(record.id());
 
-        let id = std::str::from_utf8(record.id())?;
+        let (compressed_sequence, compressed_qual, ranges) =
+            homopolymer_compression(&seq, record.qual(), &opts);
 
-        // Id.
-        writer.write_all(b">")?;
-        writer.write_all(id.as_bytes())?;
-        writer.write_all(b"\n")?;
+        writer.write(&header, &compressed_sequence, compressed_qual.as_deref())?;
 
-        // Sequence
-        writer.write_all(&compressed_sequence)?;
-        writer.write_all(b"\n")?;
+        if let Some(map_writer) = &mut map_writer {
+            write_blocks(map_writer, header.id, &blocks(&ranges, seq.len()))?;
+        }
     }
 
-    writer.flush()?;
+    writer.finish()?;
+
+    if let Some(map_writer) = &mut map_writer {
+        map_writer.finish()?;
+    }
 
     Ok(())
 }
@@ -72,5 +184,71 @@ pub fn fasta_compress(
 #[case(b"AAATTTCCCGGG", 10, b"AAATTTCCCGGG")]
 
 fn test_compression(#[case] seq: &[u8], #[case] max_hp_len: usize, #[case] expected_seq: &[u8]) {
-    assert_eq!(&homopolymer_compression(seq, max_hp_len)[..], expected_seq);
+    assert_eq!(
+        &homopolymer_compression(
+            seq,
+            None,
+            &CompressOpts {
+                max_hp_len,
+                ..Default::default()
+            }
+        )
+        .0[..],
+        expected_seq
+    );
+}
+
+#[rstest]
+#[case(b"AAATTC", b"ABCDEF", 2, b"AATTC", b"ABDEF")]
+#[case(b"AAATTC", b"ABCDEF", 1, b"ATC", b"ADF")]
+
+fn test_compression_qual(
+    #[case] seq: &[u8],
+    #[case] qual: &[u8],
+    #[case] max_hp_len: usize,
+    #[case] expected_seq: &[u8],
+    #[case] expected_qual: &[u8],
+) {
+    let opts = CompressOpts {
+        max_hp_len,
+        ..Default::default()
+    };
+    let (compressed_seq, compressed_qual, _) = homopolymer_compression(seq, Some(qual), &opts);
+
+    assert_eq!(&compressed_seq[..], expected_seq);
+    assert_eq!(compressed_qual.as_deref(), Some(expected_qual));
+}
+
+#[rstest]
+#[case(b"AAaaCC", false, false, None, b"AAaaCC")]
+#[case(b"AAaaCC", true, false, None, b"AACC")]
+#[case(b"aAAaCC", true, false, None, b"aACC")]
+#[case(b"ANNNNNA", false, false, None, b"ANNA")]
+#[case(b"ANNNNNA", false, true, None, b"ANNNNNA")]
+#[case(b"GACACACACT", false, false, Some(2), b"GACACT")]
+// Trailing partial copy.
+#[case(b"ACACACA", false, false, Some(1), b"ACA")]
+#[case(b"ACacAC", true, false, Some(2), b"ACac")]
+// Homopolymers take precedence.
+#[case(b"AAAGAGAG", false, false, Some(1), b"AAGAG")]
+#[case(b"NANANA", false, true, Some(1), b"NANANA")]
+
+fn test_compression_opts(
+    #[case] seq: &[u8],
+    #[case] ignore_case: bool,
+    #[case] skip_n: bool,
+    #[case] max_dinuc_copies: Option<usize>,
+    #[case] expected_seq: &[u8],
+) {
+    let opts = CompressOpts {
+        max_hp_len: 2,
+        ignore_case,
+        skip_n,
+        max_dinuc_copies,
+    };
+
+    assert_eq!(
+        &homopolymer_compression(seq, None, &opts).0[..],
+        expected_seq
+    );
 }
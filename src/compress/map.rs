@@ -0,0 +1,205 @@
+use crate::errors::AppError;
+use crate::fastx::OutputWriter;
+use crate::fastx::reader::open_input;
+#[cfg(test)]
+use crate::{args::CompressType, fastx::writer::write_temp_file};
+use rstest::*;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Stretch of bases kept unchanged by homopolymer compression, i.e. compressed
+/// positions `compressed_start..compressed_start + len` are the original positions
+/// `original_start..original_start + len`. Bases between two blocks were removed
+/// from a homopolymer ending at the last base of the first block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Block {
+    pub compressed_start: usize,
+    pub original_start: usize,
+    pub len: usize,
+}
+
+/// Blocks from the kept ranges of a sequence of length `seq_len`. The last block has length 0
+/// and holds the compressed and original sequence lengths.
+pub fn blocks(ranges: &[Range<usize>], seq_len: usize) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut compressed_len: usize = 0;
+
+    for range in ranges {
+        match blocks.last_mut() {
+            Some(block) if block.original_start + block.len == range.start => {
+                block.len += range.len()
+            }
+            _ => blocks.push(Block {
+                compressed_start: compressed_len,
+                original_start: range.start,
+                len: range.len(),
+            }),
+        }
+
+        compressed_len += range.len();
+    }
+
+    blocks.push(Block {
+        compressed_start: compressed_len,
+        original_start: seq_len,
+        len: 0,
+    });
+
+    blocks
+}
+
+pub fn write_blocks(
+    writer: &mut OutputWriter,
+    id: &[u8],
+    blocks: &[Block],
+) -> Result<(), AppError> {
+    for block in blocks {
+        writer.write_all(id)?;
+        writeln!(
+            writer,
+            "\t{}\t{}\t{}",
+            block.compressed_start, block.original_start, block.len
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Position in `blocks` of the block containing `position`, according to `start`.
+fn containing_block(blocks: &[Block], position: usize, start: fn(&Block) -> usize) -> &Block {
+    let i = blocks.partition_point(|block| start(block) <= position);
+    &blocks[i.saturating_sub(1)]
+}
+
+/// Maps a compressed interval to original coordinates. An interval ending at the last
+/// kept base of a compressed homopolymer includes the removed bases of the homopolymer.
+pub fn to_original(blocks: &[Block], start: usize, end: usize) -> (usize, usize) {
+    let original = |position: usize| {
+        let block = containing_block(blocks, position, |block| block.compressed_start);
+        block.original_start + (position - block.compressed_start).min(block.len)
+    };
+
+    let original_end = match end {
+        0 => 0,
+        end => {
+            let i = blocks.partition_point(|block| block.compressed_start < end);
+            match blocks.get(i) {
+                // The interval ends at a block boundary.
+                Some(next) if next.compressed_start == end => next.original_start,
+                _ => original(end),
+            }
+        }
+    };
+
+    (original(start).min(original_end), original_end)
+}
+
+/// Maps an original interval to compressed coordinates. Removed bases of a homopolymer
+/// map to the last kept base of the homopolymer.
+pub fn to_compressed(blocks: &[Block], start: usize, end: usize) -> (usize, usize) {
+    let compressed = |position: usize| {
+        let block = containing_block(blocks, position, |block| block.original_start);
+        block.compressed_start + (position - block.original_start).min(block.len.saturating_sub(1))
+    };
+
+    let compressed_end = match end {
+        0 => 0,
+        end => compressed(end - 1) + 1,
+    };
+
+    (compressed(start).min(compressed_end), compressed_end)
+}
+
+/// Blocks of each record, as written by `compress --map`.
+pub fn read_map(path: &Path) -> Result<HashMap<String, Vec<Block>>, AppError> {
+    let reader = open_input(Some(path))?;
+
+    let mut map: HashMap<String, Vec<Block>> = HashMap::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 4 {
+            return Err(AppError::InvalidArgError(format!(
+                "invalid compression map line: {line}"
+            )));
+        }
+
+        map.entry(fields[0].to_string()).or_default().push(Block {
+            compressed_start: fields[1].parse()?,
+            original_start: fields[2].parse()?,
+            len: fields[3].parse()?,
+        });
+    }
+
+    Ok(map)
+}
+
+#[rstest]
+// AAAAACGTTTT compressed with max length 2 to AACGTT.
+#[case(vec![0..2, 5..6, 6..7, 7..9], 11, vec![(0, 0, 2), (2, 5, 4), (6, 11, 0)])]
+#[case(vec![0..4], 4, vec![(0, 0, 4), (4, 4, 0)])]
+#[case(vec![], 0, vec![(0, 0, 0)])]
+
+fn test_blocks(
+    #[case] ranges: Vec<Range<usize>>,
+    #[case] seq_len: usize,
+    #[case] expected: Vec<(usize, usize, usize)>,
+) {
+    let blocks: Vec<(usize, usize, usize)> = blocks(&ranges, seq_len)
+        .iter()
+        .map(|block| (block.compressed_start, block.original_start, block.len))
+        .collect();
+
+    assert_eq!(blocks, expected);
+}
+
+#[rstest]
+// AAAAACGTTTT <-> AACGTT
+#[case((0, 2), (0, 5))]
+#[case((1, 3), (1, 6))]
+#[case((2, 4), (5, 7))]
+#[case((4, 6), (7, 11))]
+#[case((5, 6), (8, 11))]
+#[case((0, 0), (0, 0))]
+
+fn test_to_original(#[case] compressed: (usize, usize), #[case] expected: (usize, usize)) {
+    let blocks = blocks(&[0..2, 5..9], 11);
+
+    assert_eq!(to_original(&blocks, compressed.0, compressed.1), expected);
+}
+
+#[rstest]
+#[case((0, 5), (0, 2))]
+#[case((3, 6), (1, 3))]
+#[case((5, 7), (2, 4))]
+#[case((8, 11), (5, 6))]
+#[case((10, 11), (5, 6))]
+
+fn test_to_compressed(#[case] original: (usize, usize), #[case] expected: (usize, usize)) {
+    let blocks = blocks(&[0..2, 5..9], 11);
+
+    assert_eq!(to_compressed(&blocks, original.0, original.1), expected);
+}
+
+#[rstest]
+#[case(CompressType::Gzip)]
+#[case(CompressType::Bgzip)]
+#[case(CompressType::Zstd)]
+#[case(CompressType::Xz)]
+
+fn test_compressed_map(#[case] compress_type: CompressType) {
+    let (_dir, path) = write_temp_file("map.tsv", Some(compress_type), |writer| {
+        write_blocks(writer, b"seq_1", &blocks(&[0..2, 5..9], 11))
+    });
+
+    let map = read_map(&path).unwrap();
+    assert_eq!(to_original(&map["seq_1"], 2, 4), (5, 7));
+    assert_eq!(to_original(&map["seq_1"], 4, 6), (7, 11));
+}
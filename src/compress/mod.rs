@@ -1,3 +1,5 @@
 #[allow(clippy::module_inception)]
 pub mod compress;
 pub use compress::fasta_compress;
+
+pub mod map;
@@ -1,51 +1,96 @@
 use crate::amplicon::fasta_amplicon;
-use crate::args::{App, SubCommand};
+use crate::args::{App, PrimersCommand, SubCommand};
 use crate::chunk::fasta_chunk;
 use crate::compress::fasta_compress;
 use crate::errors::AppError;
+use crate::expand::fasta_expand;
 use crate::extract::fasta_extract;
+use crate::fa2fq::fasta_fa2fq;
 use crate::fa2tab::fasta_fa2tab;
+use crate::fa2twobit::fasta_fa2twobit;
+use crate::faidx::fasta_faidx;
+use crate::fastx::WriterOpts;
 use crate::filter::fasta_filter;
+use crate::fq2fa::fasta_fq2fa;
+use crate::gb2fa::fasta_gb2fa;
 use crate::grep::fasta_grep;
 use crate::head::fasta_head;
 use crate::homopolymers::fasta_homopolymers;
+use crate::kmers::fasta_kmers;
+use crate::lift::fasta_lift;
+use crate::mask::fasta_mask;
+use crate::primers::fasta_primers_check;
+use crate::repeats::fasta_repeats;
 use crate::reverse::fasta_reverse;
 use crate::sample::fasta_sample;
 use crate::shuffle::fasta_shuffle;
+use crate::sketch::{fasta_dist, fasta_sketch};
+use crate::sliding::fasta_sliding;
 use crate::sort::fasta_sort;
 use crate::split::fasta_split;
 use crate::stats::fasta_stats;
+use crate::twobit2fa::fasta_twobit2fa;
+use crate::window::fasta_window;
 
 pub fn dispatch(args: App) -> Result<(), AppError> {
+    let writer_opts = WriterOpts::from(&args.global_opts);
+
     match args.command {
-        SubCommand::Split { fasta, outdir } => fasta_split(fasta, &outdir)?,
+        SubCommand::Split { fasta, outdir } => fasta_split(fasta, &outdir, &writer_opts)?,
         SubCommand::Stats { fasta, outfile } => {
-            let _ = fasta_stats(fasta, outfile)?;
+            let _ = fasta_stats(fasta, outfile, &writer_opts)?;
         }
-        SubCommand::Fa2tab { fasta, outfile } => fasta_fa2tab(fasta, outfile)?,
+        SubCommand::Fa2tab { fasta, outfile } => fasta_fa2tab(fasta, outfile, &writer_opts)?,
         SubCommand::Head {
             fasta,
             num_seqs,
+            skip,
             outfile,
-        } => fasta_head(fasta, num_seqs, outfile)?,
+        } => fasta_head(fasta, num_seqs, skip, outfile, &writer_opts)?,
         SubCommand::Grep {
-            fastq,
+            fasta,
             pattern,
             outfile,
-        } => fasta_grep(fastq, pattern, outfile)?,
+        } => fasta_grep(fasta, pattern, outfile, &writer_opts)?,
         SubCommand::Homopolymers {
             fasta,
             min_hp_len,
             strict,
+            output_format,
             outfile,
-        } => fasta_homopolymers(fasta, min_hp_len, strict, outfile)?,
+        } => fasta_homopolymers(
+            fasta,
+            min_hp_len,
+            strict,
+            &output_format,
+            outfile,
+            &writer_opts,
+        )?,
+        SubCommand::Repeats {
+            fasta,
+            min_period,
+            max_period,
+            min_copies,
+            imperfect,
+            min_purity,
+            outfile,
+        } => fasta_repeats(
+            fasta,
+            min_period,
+            max_period,
+            min_copies,
+            imperfect,
+            min_purity,
+            outfile,
+            &writer_opts,
+        )?,
         SubCommand::Sort {
             fasta,
             by,
             reverse,
             outfile,
-        } => fasta_sort(fasta, by, reverse, outfile)?,
-        SubCommand::Shuffle { fasta, outfile } => fasta_shuffle(fasta, outfile)?,
+        } => fasta_sort(fasta, by, reverse, outfile, &writer_opts)?,
+        SubCommand::Shuffle { fasta, outfile } => fasta_shuffle(fasta, outfile, &writer_opts)?,
         SubCommand::Filter {
             fasta,
             min_len,
@@ -58,6 +103,8 @@ pub fn dispatch(args: App) -> Result<(), AppError> {
             max_softmask,
             min_entropy,
             max_entropy,
+            min_qual,
+            max_ee,
             outfile,
         } => fasta_filter(
             fasta,
@@ -71,32 +118,223 @@ pub fn dispatch(args: App) -> Result<(), AppError> {
             max_softmask,
             min_entropy,
             max_entropy,
+            min_qual,
+            max_ee,
             outfile,
+            &writer_opts,
         )?,
         SubCommand::Extract {
             fasta,
             start,
             end,
             outfile,
-        } => fasta_extract(fasta, start, end, outfile)?,
-        SubCommand::Sample { fasta, by, outfile } => fasta_sample(fasta, by, outfile)?,
+        } => fasta_extract(fasta, start, end, outfile, &writer_opts)?,
+        SubCommand::Sliding {
+            fasta,
+            window,
+            step,
+            circular,
+            outfile,
+        } => fasta_sliding(fasta, window, step, circular, outfile, &writer_opts)?,
+        SubCommand::Sample { fasta, by, outfile } => {
+            fasta_sample(fasta, by, outfile, &writer_opts)?
+        }
         SubCommand::Amplicon {
             fasta,
             primers,
             search_type,
+            min_len,
+            max_len,
+            forward_only,
+            include_primers,
+            output_format,
+            outfile,
+        } => fasta_amplicon(
+            fasta,
+            &primers,
+            &search_type,
+            min_len,
+            max_len,
+            forward_only,
+            include_primers,
+            &output_format,
+            outfile,
+            &writer_opts,
+        )?,
+        SubCommand::Expand {
+            fasta,
+            max_expansions,
+            outfile,
+        } => fasta_expand(fasta, max_expansions, outfile, &writer_opts)?,
+        SubCommand::Primers { command } => match command {
+            PrimersCommand::Check {
+                primers,
+                background,
+                search_type,
+                min_len,
+                max_len,
+                min_dimer_len,
+                outfile,
+            } => fasta_primers_check(
+                &primers,
+                background,
+                &search_type,
+                min_len,
+                max_len,
+                min_dimer_len,
+                outfile,
+                &writer_opts,
+            )?,
+        },
+        SubCommand::Kmers {
+            fasta,
+            kmer_size,
+            canonical,
+            top,
+            profiles,
+            outfile,
+        } => fasta_kmers(
+            fasta,
+            kmer_size,
+            canonical,
+            top,
+            profiles,
+            outfile,
+            &writer_opts,
+        )?,
+        SubCommand::Sketch {
+            fasta,
+            kmer_size,
+            sketch_size,
+            scaled,
+            per_record,
+            outfile,
+        } => fasta_sketch(
+            fasta,
+            kmer_size,
+            sketch_size,
+            scaled,
+            per_record,
+            outfile,
+            &writer_opts,
+        )?,
+        SubCommand::Dist {
+            sketches,
+            output_format,
+            outfile,
+        } => fasta_dist(sketches, &output_format, outfile, &writer_opts)?,
+        SubCommand::Window {
+            fasta,
+            window,
+            step,
+            output_format,
+            metric,
             outfile,
-        } => fasta_amplicon(fasta, &primers, &search_type, outfile)?,
+        } => fasta_window(
+            fasta,
+            window,
+            step,
+            &output_format,
+            &metric,
+            outfile,
+            &writer_opts,
+        )?,
         SubCommand::Compress {
             fasta,
             max_hp_len,
+            ignore_case,
+            skip_n,
+            max_dinuc_copies,
+            map,
             outfile,
-        } => fasta_compress(fasta, max_hp_len, outfile)?,
-        SubCommand::Reverse { fasta, outfile } => fasta_reverse(fasta, outfile)?,
+        } => fasta_compress(
+            fasta,
+            max_hp_len,
+            ignore_case,
+            skip_n,
+            max_dinuc_copies,
+            map,
+            outfile,
+            &writer_opts,
+        )?,
+        SubCommand::Mask {
+            fasta,
+            threshold,
+            window,
+            mask_mode,
+            bed,
+            outfile,
+        } => fasta_mask(
+            fasta,
+            threshold,
+            window,
+            mask_mode,
+            bed,
+            outfile,
+            &writer_opts,
+        )?,
+        SubCommand::Lift {
+            map,
+            bed,
+            to,
+            outfile,
+        } => fasta_lift(&map, bed, &to, outfile, &writer_opts)?,
+        SubCommand::Reverse {
+            fasta,
+            mode,
+            tag,
+            ids,
+            outfile,
+        } => fasta_reverse(fasta, mode, tag, ids, outfile, &writer_opts)?,
+        SubCommand::Faidx {
+            fasta,
+            regions,
+            bed,
+            outfile,
+        } => fasta_faidx(&fasta, regions, bed, outfile, &writer_opts)?,
+        SubCommand::Fq2fa {
+            fastq,
+            mask_qual,
+            mask_mode,
+            outfile,
+        } => fasta_fq2fa(fastq, mask_qual, mask_mode, outfile, &writer_opts)?,
+        SubCommand::Fa2fq {
+            fasta,
+            qual,
+            softmask_qual,
+            ambig_qual,
+            outfile,
+        } => fasta_fa2fq(
+            fasta,
+            qual,
+            softmask_qual,
+            ambig_qual,
+            outfile,
+            &writer_opts,
+        )?,
+        SubCommand::Fa2twobit { fasta, outfile } => fasta_fa2twobit(fasta, &outfile)?,
+        SubCommand::Twobit2fa { twobit, outfile } => {
+            fasta_twobit2fa(&twobit, outfile, &writer_opts)?
+        }
+        SubCommand::Gb2fa {
+            genbank,
+            features,
+            qualifiers,
+            features_only,
+            outfile,
+        } => fasta_gb2fa(
+            genbank,
+            features,
+            qualifiers,
+            features_only,
+            outfile,
+            &writer_opts,
+        )?,
         SubCommand::Chunk {
             fasta,
             num_contigs_per_file,
             outdir,
-        } => fasta_chunk(fasta, num_contigs_per_file, &outdir)?,
+        } => fasta_chunk(fasta, num_contigs_per_file, &outdir, &writer_opts)?,
     };
 
     Ok(())
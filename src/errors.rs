@@ -20,9 +20,18 @@ pub enum AppError {
     #[error("Invalid argument: {0}")]
     InvalidArgError(String),
 
+    #[error("Invalid index: {0}")]
+    InvalidIndexError(String),
+
+    #[error("Sequence not found: {0}")]
+    SequenceNotFoundError(String),
+
     #[error("Invalid regex pattern: {0}")]
     InvalidRegexPattern(String),
 
+    #[error("Failed to parse GenBank/EMBL file: {0}")]
+    FlatFileParseError(String),
+
     #[error(transparent)]
     BioError(#[from] BioError),
 }
@@ -51,6 +60,12 @@ impl From<std::num::ParseIntError> for AppError {
     }
 }
 
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::BioError(BioError::SerializationError(err))
+    }
+}
+
 impl From<regex::Error> for AppError {
     fn from(err: regex::Error) -> Self {
         AppError::InvalidRegexPattern(err.to_string())
@@ -0,0 +1,48 @@
+use crate::errors::AppError;
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader};
+use crate::iupac::{expand, expansion_count};
+use std::path::PathBuf;
+
+/// Writes every concrete sequence represented by each (degenerate) record as `<id>_<n>`.
+/// Records with more than `max_expansions` sequences are skipped with a warning.
+pub fn fasta_expand(
+    fasta: Option<PathBuf>,
+    max_expansions: usize,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let mut reader = fastx_reader(fasta)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        let header = Header::parse(record.id());
+        let seq = record.seq();
+
+        match expansion_count(&seq) {
+            Some(count) if count <= max_expansions => {}
+            count => {
+                log::warn!(
+                    "Skipping {}, it represents {} sequences (max {})",
+                    String::from_utf8_lossy(header.id),
+                    count.map_or("too many".to_string(), |count| count.to_string()),
+                    max_expansions
+                );
+                continue;
+            }
+        }
+
+        for (i, expanded) in expand(&seq).iter().enumerate() {
+            let id = [header.id, format!("_{}", i + 1).as_bytes()].concat();
+            writer.write(&header.with_id(&id), expanded, None)?;
+        }
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
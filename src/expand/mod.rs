@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod expand;
+pub use expand::fasta_expand;
@@ -1,20 +1,56 @@
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
-use std::{io::Write, path::PathBuf};
+use crate::fastx::{FastxWriter, Header, TwoBitFile, WriterOpts, fastx_reader};
+use std::path::{Path, PathBuf};
+
+/// 2bit input is fetched per sequence, only reading the requested range.
+fn extract_twobit(
+    twobit: &Path,
+    start: usize,
+    end: usize,
+    writer: &mut FastxWriter,
+) -> Result<(), AppError> {
+    let twobit = TwoBitFile::open(twobit)?;
+
+    for name in twobit.names() {
+        let max_end = (end as u64).min(twobit.length(name)?);
+
+        if start as u64 >= max_end {
+            continue;
+        }
+
+        let seq = twobit.fetch(name, start as u64, max_end)?;
+        let id = format!("{}|{}-{}", name, start, max_end);
+
+        writer.write(&Header::parse(id.as_bytes()), &seq, None)?;
+    }
+
+    Ok(())
+}
 
 pub fn fasta_extract(
     fasta: Option<PathBuf>,
     start: usize,
     end: usize,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
-    let mut reader = needletail_reader(fasta)?;
-
     if start >= end {
         return Err(AppError::InvalidRangeError);
     }
 
-    let mut writer = get_bufwriter(outfile)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    if let Some(twobit) = fasta
+        .as_deref()
+        .filter(|f| TwoBitFile::is_twobit(f).unwrap_or(false))
+    {
+        extract_twobit(twobit, start, end, &mut writer)?;
+        writer.finish()?;
+
+        return Ok(());
+    }
+
+    let mut reader = fastx_reader(fasta)?;
 
     while let Some(record) = reader.next() {
         let record = match record {
@@ -30,22 +66,20 @@ pub fn fasta_extract(
 
         let max_end = end.min(record_seq.len());
 
-        let record_id = std::str::from_utf8(record.id())?;
+        let header = Header::parse(record.id());
+        let record_id = std::str::from_utf8(header.id)?;
 
         // Add start/end coordinates.
         let id = format!("{}|{}-{}", record_id, start, max_end);
 
-        // Id.
-        writer.write_all(b">")?;
-        writer.write_all(id.as_bytes())?;
-        writer.write_all(b"\n")?;
-
-        // Sequence.
-        writer.write_all(&record.seq()[start..max_end])?;
-        writer.write_all(b"\n")?;
+        writer.write(
+            &header.with_id(id.as_bytes()),
+            &record_seq[start..max_end],
+            record.qual().map(|qual| &qual[start..max_end]),
+        )?;
     }
 
-    writer.flush()?;
+    writer.finish()?;
 
     Ok(())
 }
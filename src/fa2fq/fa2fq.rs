@@ -0,0 +1,87 @@
+use crate::errors::AppError;
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader};
+use bio_utils_rs::nucleotide::PHRED_OFFSET;
+use rstest::*;
+use std::path::PathBuf;
+
+/// Highest Phred score that is printable in Phred+33 (`~`).
+const MAX_QUAL: u8 = 93;
+
+/// Per-base synthetic quality string. Canonical bases get `qual`, while softmasked
+/// and ambiguous bases optionally get their own quality.
+fn synthetic_qual(
+    seq: &[u8],
+    qual: u8,
+    softmask_qual: Option<u8>,
+    ambig_qual: Option<u8>,
+) -> Vec<u8> {
+    seq.iter()
+        .map(|nt| {
+            let q = match nt {
+                b'A' | b'C' | b'G' | b'T' => qual,
+                b'a' | b'c' | b'g' | b't' => softmask_qual.unwrap_or(qual),
+                _ => ambig_qual.unwrap_or(qual),
+            };
+
+            q + PHRED_OFFSET as u8
+        })
+        .collect()
+}
+
+pub fn fasta_fa2fq(
+    fasta: Option<PathBuf>,
+    qual: u8,
+    softmask_qual: Option<u8>,
+    ambig_qual: Option<u8>,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    for q in [Some(qual), softmask_qual, ambig_qual]
+        .into_iter()
+        .flatten()
+    {
+        if q > MAX_QUAL {
+            return Err(AppError::InvalidArgError(format!(
+                "quality must be <= {MAX_QUAL}, got {q}"
+            )));
+        }
+    }
+
+    let mut reader = fastx_reader(fasta)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        let seq = record.seq();
+        let qual = synthetic_qual(&seq, qual, softmask_qual, ambig_qual);
+
+        writer.write(&Header::parse(record.id()), &seq, Some(&qual))?;
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(b"", 40, None, None, b"")]
+#[case(b"ACGT", 40, None, None, b"IIII")]
+#[case(b"ACgtN", 40, None, None, b"IIIII")]
+#[case(b"ACgtN", 40, Some(20), Some(0), b"II55!")]
+
+fn test_synthetic_qual(
+    #[case] seq: &[u8],
+    #[case] qual: u8,
+    #[case] softmask_qual: Option<u8>,
+    #[case] ambig_qual: Option<u8>,
+    #[case] expected: &[u8],
+) {
+    assert_eq!(
+        &synthetic_qual(seq, qual, softmask_qual, ambig_qual)[..],
+        expected
+    );
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod fa2fq;
+pub use fa2fq::fasta_fa2fq;
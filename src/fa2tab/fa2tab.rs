@@ -1,14 +1,18 @@
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
+use crate::fastx::{WriterOpts, fastx_reader, get_writer};
 use bio_utils_rs::nucleotide::{
     gc_content, nucleotide_counts, nucleotide_probabilities, shannon_entropy,
 };
 use std::{io::Write, path::PathBuf};
 
-pub fn fasta_fa2tab(fasta: Option<PathBuf>, outfile: Option<PathBuf>) -> Result<(), AppError> {
-    let mut reader = needletail_reader(fasta)?;
+pub fn fasta_fa2tab(
+    fasta: Option<PathBuf>,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let mut reader = fastx_reader(fasta)?;
 
-    let mut writer = get_bufwriter(outfile)?;
+    let mut writer = get_writer(outfile, writer_opts)?;
 
     writer.write_all(b"id\tlength\tgc_content\tentropy\tnum_softmasked\tnum_ambiguous\n")?;
 
@@ -52,7 +56,7 @@ pub fn fasta_fa2tab(fasta: Option<PathBuf>, outfile: Option<PathBuf>) -> Result<
         writer.write_all(b"\n")?;
     }
 
-    writer.flush()?;
+    writer.finish()?;
 
     Ok(())
 }
@@ -0,0 +1,30 @@
+use crate::errors::AppError;
+use crate::fastx::twobit::{TwoBitRecord, write_twobit};
+use crate::fastx::{Header, fastx_reader};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+pub fn fasta_fa2twobit(fasta: Option<PathBuf>, outfile: &Path) -> Result<(), AppError> {
+    let mut reader = fastx_reader(fasta)?;
+
+    // Records are kept packed in memory, since the index precedes the sequences.
+    let mut records: Vec<TwoBitRecord> = Vec::new();
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        let header = Header::parse(record.id());
+        let name = std::str::from_utf8(header.id)?;
+
+        records.push(TwoBitRecord::encode(name, &record.seq())?);
+    }
+
+    let mut writer = BufWriter::new(File::create(outfile)?);
+    write_twobit(&mut writer, &records)?;
+
+    Ok(())
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod fa2twobit;
+pub use fa2twobit::fasta_fa2twobit;
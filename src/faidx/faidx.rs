@@ -0,0 +1,159 @@
+use crate::errors::AppError;
+use crate::fastx::fai::build_index;
+use crate::fastx::{FastxWriter, Header, TwoBitFile, WriterOpts, open_random_access};
+use rayon::prelude::*;
+use rstest::*;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq)]
+struct Region {
+    name: String,
+    start: u64,
+    end: Option<u64>,
+}
+
+/// Parses `name` or `name:start-end`, with 1-based inclusive coordinates like
+/// samtools faidx, into a 0-based half-open region. Names containing `:` are supported as
+/// long as the suffix is not a valid range.
+fn parse_region(region: &str) -> Result<Region, AppError> {
+    let whole = Region {
+        name: region.to_string(),
+        start: 0,
+        end: None,
+    };
+
+    let Some((name, range)) = region.rsplit_once(':') else {
+        return Ok(whole);
+    };
+
+    let Some((start, end)) = range.split_once('-') else {
+        return Ok(whole);
+    };
+
+    let (Ok(start), Ok(end)) = (
+        start.replace(',', "").parse::<u64>(),
+        end.replace(',', "").parse::<u64>(),
+    ) else {
+        return Ok(whole);
+    };
+
+    if start == 0 || start > end {
+        return Err(AppError::InvalidRangeError);
+    }
+
+    Ok(Region {
+        name: name.to_string(),
+        start: start - 1,
+        end: Some(end),
+    })
+}
+
+fn read_bed(bed: &PathBuf) -> Result<Vec<Region>, AppError> {
+    let reader = BufReader::new(File::open(bed)?);
+
+    let mut regions: Vec<Region> = Vec::new();
+    for line in reader.lines().map_while(Result::ok) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with("track") {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 {
+            return Err(AppError::InvalidArgError(format!(
+                "invalid BED line: {line}"
+            )));
+        }
+
+        regions.push(Region {
+            name: fields[0].to_string(),
+            start: fields[1].parse()?,
+            end: Some(fields[2].parse()?),
+        });
+    }
+
+    Ok(regions)
+}
+
+pub fn fasta_faidx(
+    fasta: &Path,
+    regions: Vec<String>,
+    bed: Option<PathBuf>,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    // Without regions, we only (re)build the index.
+    if regions.is_empty() && bed.is_none() {
+        if TwoBitFile::is_twobit(fasta)? {
+            return Err(AppError::InvalidArgError(
+                "2bit files are already indexed, specify regions to fetch".into(),
+            ));
+        }
+
+        return build_index(fasta);
+    }
+
+    let index = open_random_access(fasta)?;
+
+    let mut parsed_regions: Vec<Region> = regions
+        .iter()
+        .map(|r| parse_region(r))
+        .collect::<Result<_, _>>()?;
+
+    if let Some(bed) = bed {
+        parsed_regions.extend(read_bed(&bed)?);
+    }
+
+    // Each region is fetched with its own file handle, so this runs in parallel
+    // also for BGZF files. Output is kept in the order the regions were given.
+    let sequences: Vec<(String, Vec<u8>)> = parsed_regions
+        .par_iter()
+        .map(|region| {
+            let seq = index.fetch(&region.name, region.start, region.end.unwrap_or(u64::MAX))?;
+
+            // Named with BED coordinates, like `extract`.
+            let id = match region.end {
+                Some(_) => format!(
+                    "{}|{}-{}",
+                    region.name,
+                    region.start,
+                    region.start + seq.len() as u64
+                ),
+                None => region.name.clone(),
+            };
+
+            Ok((id, seq))
+        })
+        .collect::<Result<_, AppError>>()?;
+
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    for (id, seq) in sequences {
+        writer.write(&Header::parse(id.as_bytes()), &seq, None)?;
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case("chr1", Region { name: "chr1".into(), start: 0, end: None })]
+#[case("chr1:1-10", Region { name: "chr1".into(), start: 0, end: Some(10) })]
+#[case("chr1:10-10", Region { name: "chr1".into(), start: 9, end: Some(10) })]
+#[case("chr1:1,001-2,000", Region { name: "chr1".into(), start: 1000, end: Some(2000) })]
+#[case("HLA:A*01:01", Region { name: "HLA:A*01:01".into(), start: 0, end: None })]
+#[case("HLA:A*01:01:6-10", Region { name: "HLA:A*01:01".into(), start: 5, end: Some(10) })]
+
+fn test_parse_region(#[case] region: &str, #[case] expected: Region) {
+    assert_eq!(parse_region(region).unwrap(), expected);
+}
+
+#[rstest]
+#[case("chr1:0-10")]
+#[case("chr1:10-9")]
+
+fn test_parse_region_invalid(#[case] region: &str) {
+    assert!(parse_region(region).is_err());
+}
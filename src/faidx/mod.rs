@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod faidx;
+pub use faidx::fasta_faidx;
@@ -0,0 +1,72 @@
+use crate::errors::AppError;
+use crate::fastx::OwnedRecord;
+use needletail::FastxReader;
+use rstest::*;
+
+/// Records are read in batches of about this many bases, which are processed in parallel.
+const BATCH_SIZE: usize = 16 * 1024 * 1024;
+
+/// Calls `f` on batches of valid records in input order, so that subcommands can process
+/// the records of a batch in parallel without keeping the whole input in memory.
+pub fn for_each_batch<F>(reader: Box<dyn FastxReader>, f: F) -> Result<(), AppError>
+where
+    F: FnMut(&[OwnedRecord]) -> Result<(), AppError>,
+{
+    batches(reader, BATCH_SIZE, f)
+}
+
+fn batches<F>(mut reader: Box<dyn FastxReader>, batch_size: usize, mut f: F) -> Result<(), AppError>
+where
+    F: FnMut(&[OwnedRecord]) -> Result<(), AppError>,
+{
+    let mut batch: Vec<OwnedRecord> = Vec::new();
+    let mut batch_bases: usize = 0;
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        batch_bases += record.num_bases();
+        batch.push(OwnedRecord::from(&record));
+
+        if batch_bases >= batch_size {
+            f(&batch)?;
+            batch.clear();
+            batch_bases = 0;
+        }
+    }
+
+    if !batch.is_empty() {
+        f(&batch)?;
+    }
+
+    Ok(())
+}
+
+#[rstest]
+#[case(b">a\nACGT\n>b\nAC\n>c\nACG\n", 4, vec![vec!["a"], vec!["b", "c"]])]
+#[case(b">a\nACGT\n>b\nAC\n>c\nACG\n", 100, vec![vec!["a", "b", "c"]])]
+
+fn test_batches(
+    #[case] input: &'static [u8],
+    #[case] batch_size: usize,
+    #[case] expected: Vec<Vec<&str>>,
+) {
+    let reader = needletail::parse_fastx_reader(input).unwrap();
+    let mut ids: Vec<Vec<String>> = Vec::new();
+
+    batches(reader, batch_size, |batch| {
+        ids.push(
+            batch
+                .iter()
+                .map(|record| String::from_utf8_lossy(&record.header).into_owned())
+                .collect(),
+        );
+        Ok(())
+    })
+    .unwrap();
+
+    assert_eq!(ids, expected);
+}
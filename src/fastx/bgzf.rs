@@ -0,0 +1,183 @@
+use crate::errors::AppError;
+use flate2::read::MultiGzDecoder;
+use rstest::*;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// A `.gzi` entry, mapping the compressed offset of a BGZF block
+/// to the uncompressed offset of its first byte.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GziEntry {
+    pub compressed: u64,
+    pub uncompressed: u64,
+}
+
+/// Returns the block size if `header` is the start of a BGZF block.
+fn bgzf_block_size(header: &[u8; 18]) -> Option<u64> {
+    let is_bgzf = header[0] == 0x1f
+        && header[1] == 0x8b
+        && header[2] == 0x08
+        && header[3] & 0x04 != 0
+        && header[12] == b'B'
+        && header[13] == b'C';
+
+    match is_bgzf {
+        true => Some(u16::from_le_bytes([header[16], header[17]]) as u64 + 1),
+        false => None,
+    }
+}
+
+pub fn is_bgzf(path: &Path) -> Result<bool, AppError> {
+    let mut header = [0u8; 18];
+
+    match File::open(path)?.read_exact(&mut header) {
+        Ok(_) => Ok(bgzf_block_size(&header).is_some()),
+        Err(_) => Ok(false),
+    }
+}
+
+/// True for any gzip file, BGZF or not.
+pub fn is_gzip(path: &Path) -> Result<bool, AppError> {
+    let mut magic = [0u8; 2];
+
+    match File::open(path)?.read_exact(&mut magic) {
+        Ok(_) => Ok(magic == [0x1f, 0x8b]),
+        Err(_) => Ok(false),
+    }
+}
+
+pub fn gzi_path(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.gzi", path.display()))
+}
+
+/// Builds the `.gzi` entries by walking the BGZF block headers, without decompressing.
+pub fn build_gzi(path: &Path) -> Result<Vec<GziEntry>, AppError> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut entries: Vec<GziEntry> = Vec::new();
+    let mut compressed: u64 = 0;
+    let mut uncompressed: u64 = 0;
+    let mut header = [0u8; 18];
+    let mut footer = [0u8; 4];
+
+    while reader.read_exact(&mut header).is_ok() {
+        let block_size = bgzf_block_size(&header).ok_or(AppError::InvalidIndexError(format!(
+            "{} is not BGZF compressed",
+            path.display()
+        )))?;
+
+        // ISIZE is the last four bytes of the block.
+        reader.seek_relative(block_size as i64 - 18 - 4)?;
+        reader.read_exact(&mut footer)?;
+
+        // The first block is implicit in the index.
+        if compressed > 0 {
+            entries.push(GziEntry {
+                compressed,
+                uncompressed,
+            });
+        }
+
+        compressed += block_size;
+        uncompressed += u32::from_le_bytes(footer) as u64;
+    }
+
+    Ok(entries)
+}
+
+pub fn write_gzi(path: &Path, entries: &[GziEntry]) -> Result<(), AppError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writer.write_all(&(entries.len() as u64).to_le_bytes())?;
+    for entry in entries {
+        writer.write_all(&entry.compressed.to_le_bytes())?;
+        writer.write_all(&entry.uncompressed.to_le_bytes())?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn read_gzi(path: &Path) -> Result<Vec<GziEntry>, AppError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = [0u8; 8];
+
+    reader.read_exact(&mut buf)?;
+    let num_entries = u64::from_le_bytes(buf);
+
+    let mut entries: Vec<GziEntry> = Vec::with_capacity(num_entries as usize);
+    for _ in 0..num_entries {
+        reader.read_exact(&mut buf)?;
+        let compressed = u64::from_le_bytes(buf);
+
+        reader.read_exact(&mut buf)?;
+        let uncompressed = u64::from_le_bytes(buf);
+
+        entries.push(GziEntry {
+            compressed,
+            uncompressed,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Last block starting at or before `uncompressed_offset`.
+fn find_block(entries: &[GziEntry], uncompressed_offset: u64) -> GziEntry {
+    let i = entries.partition_point(|e| e.uncompressed <= uncompressed_offset);
+
+    match i {
+        0 => GziEntry {
+            compressed: 0,
+            uncompressed: 0,
+        },
+        i => entries[i - 1],
+    }
+}
+
+/// Opens a BGZF file for reading from `uncompressed_offset`, only decompressing
+/// from the block containing the offset.
+pub fn bgzf_reader_at(
+    path: &Path,
+    entries: &[GziEntry],
+    uncompressed_offset: u64,
+) -> Result<Box<dyn Read + Send>, AppError> {
+    let block = find_block(entries, uncompressed_offset);
+
+    let mut f = File::open(path)?;
+    f.seek(SeekFrom::Start(block.compressed))?;
+
+    let mut reader = MultiGzDecoder::new(BufReader::new(f));
+    std::io::copy(
+        &mut reader
+            .by_ref()
+            .take(uncompressed_offset - block.uncompressed),
+        &mut std::io::sink(),
+    )?;
+
+    Ok(Box::new(reader))
+}
+
+#[rstest]
+#[case(0, GziEntry { compressed: 0, uncompressed: 0 })]
+#[case(99, GziEntry { compressed: 0, uncompressed: 0 })]
+#[case(100, GziEntry { compressed: 50, uncompressed: 100 })]
+#[case(150, GziEntry { compressed: 50, uncompressed: 100 })]
+#[case(1000, GziEntry { compressed: 90, uncompressed: 200 })]
+
+fn test_find_block(#[case] uncompressed_offset: u64, #[case] expected: GziEntry) {
+    let entries = vec![
+        GziEntry {
+            compressed: 50,
+            uncompressed: 100,
+        },
+        GziEntry {
+            compressed: 90,
+            uncompressed: 200,
+        },
+    ];
+
+    assert_eq!(find_block(&entries, uncompressed_offset), expected);
+}
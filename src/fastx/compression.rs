@@ -0,0 +1,267 @@
+use crate::args::CompressType;
+use flate2::Compression;
+use flate2::Crc;
+use flate2::write::{DeflateEncoder, GzEncoder};
+use rayon::prelude::*;
+use rstest::*;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Uncompressed block size for gzip, zstd and xz. Each block becomes its own
+/// gzip member/zstd frame/xz stream, which concatenated form a valid file.
+const BLOCK_SIZE: usize = 1 << 20;
+
+/// Max uncompressed BGZF block size, same as bgzip/htslib.
+pub const BGZF_BLOCK_SIZE: usize = 0xff00;
+
+/// Empty BGZF block that marks the end of a BGZF file.
+pub const BGZF_EOF: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+impl CompressType {
+    /// Infer compression from the output file extension. Like bgzip, `.gz` output is
+    /// BGZF, which any gzip reader can decompress and which can also be indexed.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" | "bgz" => Some(CompressType::Bgzip),
+            "zst" => Some(CompressType::Zstd),
+            "xz" => Some(CompressType::Xz),
+            _ => None,
+        }
+    }
+
+    /// File extension (without dot) for compressed output files.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            CompressType::Gzip | CompressType::Bgzip => Some("gz"),
+            CompressType::Zstd => Some("zst"),
+            CompressType::Xz => Some("xz"),
+            CompressType::None => None,
+        }
+    }
+
+    fn block_size(&self) -> usize {
+        match self {
+            CompressType::Bgzip => BGZF_BLOCK_SIZE,
+            _ => BLOCK_SIZE,
+        }
+    }
+}
+
+fn gzip_block(block: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(block)?;
+    encoder.finish()
+}
+
+/// A single BGZF block, i.e. a gzip member with the `BC` extra field holding the block size.
+pub fn bgzf_block(block: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(block)?;
+    let deflated = encoder.finish()?;
+
+    let mut crc = Crc::new();
+    crc.update(block);
+
+    // Header (18 bytes) + deflated data + footer (8 bytes).
+    let block_size = 18 + deflated.len() + 8;
+
+    let mut bgzf: Vec<u8> = Vec::with_capacity(block_size);
+    bgzf.extend_from_slice(&[
+        0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 6, 0, b'B', b'C', 2, 0,
+    ]);
+    bgzf.extend_from_slice(&((block_size - 1) as u16).to_le_bytes());
+    bgzf.extend_from_slice(&deflated);
+    bgzf.extend_from_slice(&crc.sum().to_le_bytes());
+    bgzf.extend_from_slice(&(block.len() as u32).to_le_bytes());
+
+    Ok(bgzf)
+}
+
+fn zstd_block(block: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    zstd::stream::encode_all(block, zstd::DEFAULT_COMPRESSION_LEVEL)
+}
+
+fn xz_block(block: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    let mut compressed: Vec<u8> = Vec::new();
+    liblzma::read::XzEncoder::new(block, 6).read_to_end(&mut compressed)?;
+
+    Ok(compressed)
+}
+
+fn compress_block(block: &[u8], compress_type: CompressType) -> Result<Vec<u8>, std::io::Error> {
+    match compress_type {
+        CompressType::Gzip => gzip_block(block),
+        CompressType::Bgzip => bgzf_block(block),
+        CompressType::Zstd => zstd_block(block),
+        CompressType::Xz => xz_block(block),
+        CompressType::None => Ok(block.to_vec()),
+    }
+}
+
+/// Writer that splits its input into independent blocks and compresses
+/// a batch of them in parallel on the rayon pool, writing them in order.
+pub struct BlockCompressWriter<W: Write> {
+    inner: W,
+    compress_type: CompressType,
+    block_size: usize,
+    batch_size: usize,
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+impl<W: Write> BlockCompressWriter<W> {
+    pub fn new(inner: W, compress_type: CompressType) -> Self {
+        let block_size = compress_type.block_size();
+        let batch_size = block_size * rayon::current_num_threads().max(1) * 2;
+
+        BlockCompressWriter {
+            inner,
+            compress_type,
+            block_size,
+            batch_size,
+            buffer: Vec::with_capacity(batch_size),
+            finished: false,
+        }
+    }
+
+    /// Writes the remaining data and, for BGZF, the EOF marker. Must be called to catch
+    /// errors at the end of the output, dropping the writer ignores them.
+    pub fn finish(&mut self) -> Result<(), std::io::Error> {
+        if self.finished {
+            return Ok(());
+        }
+
+        self.finished = true;
+        self.flush()?;
+
+        if let CompressType::Bgzip = self.compress_type {
+            self.inner.write_all(&BGZF_EOF)?;
+        }
+
+        self.inner.flush()
+    }
+
+    /// Compresses and writes buffered data. Unless `partial` is set, only full
+    /// blocks are written and the remainder is kept for the next batch.
+    fn write_blocks(&mut self, partial: bool) -> Result<(), std::io::Error> {
+        let compress_type = self.compress_type;
+
+        let num_bytes = match partial {
+            true => self.buffer.len(),
+            false => self.buffer.len() - self.buffer.len() % self.block_size,
+        };
+
+        let compressed: Vec<Vec<u8>> = self.buffer[..num_bytes]
+            .par_chunks(self.block_size)
+            .map(|block| compress_block(block, compress_type))
+            .collect::<Result<_, _>>()?;
+
+        for block in compressed {
+            self.inner.write_all(&block)?;
+        }
+
+        self.buffer.drain(..num_bytes);
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for BlockCompressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        if self.buffer.len() >= self.batch_size {
+            self.write_blocks(false)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.write_blocks(true)?;
+        }
+
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for BlockCompressWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+#[rstest]
+#[case("out.fa.gz", Some(CompressType::Bgzip))]
+#[case("out.fa.bgz", Some(CompressType::Bgzip))]
+#[case("out.fa.zst", Some(CompressType::Zstd))]
+#[case("out.fa.xz", Some(CompressType::Xz))]
+#[case("out.fa", None)]
+
+fn test_from_path(#[case] path: &str, #[case] expected: Option<CompressType>) {
+    assert_eq!(CompressType::from_path(Path::new(path)), expected);
+}
+
+#[rstest]
+#[case(CompressType::Gzip)]
+#[case(CompressType::Bgzip)]
+#[case(CompressType::Zstd)]
+#[case(CompressType::Xz)]
+
+fn test_block_compress_roundtrip(#[case] compress_type: CompressType) {
+    let data: Vec<u8> = b">seq_1\nACGT\n".repeat(20_000);
+
+    let mut compressed: Vec<u8> = Vec::new();
+    {
+        let mut writer = BlockCompressWriter::new(&mut compressed, compress_type);
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+    }
+
+    if compress_type == CompressType::Bgzip {
+        assert!(compressed.ends_with(&BGZF_EOF));
+    }
+
+    let mut decompressed: Vec<u8> = Vec::new();
+    match compress_type {
+        CompressType::Gzip | CompressType::Bgzip => {
+            flate2::read::MultiGzDecoder::new(&compressed[..])
+                .read_to_end(&mut decompressed)
+                .unwrap();
+        }
+        CompressType::Zstd => {
+            zstd::stream::read::Decoder::new(&compressed[..])
+                .unwrap()
+                .read_to_end(&mut decompressed)
+                .unwrap();
+        }
+        CompressType::Xz => {
+            liblzma::read::XzDecoder::new_multi_decoder(&compressed[..])
+                .read_to_end(&mut decompressed)
+                .unwrap();
+        }
+        CompressType::None => unreachable!(),
+    }
+
+    assert_eq!(decompressed, data);
+}
+
+#[rstest]
+#[case(CompressType::Gzip)]
+#[case(CompressType::Bgzip)]
+#[case(CompressType::Zstd)]
+#[case(CompressType::Xz)]
+
+fn test_block_compress_finish_error(#[case] compress_type: CompressType) {
+    // Too small for the compressed output, like a full disk.
+    let mut buf = [0u8; 8];
+
+    let mut writer = BlockCompressWriter::new(std::io::Cursor::new(&mut buf[..]), compress_type);
+    writer.write_all(b">seq_1\nACGT\n").unwrap();
+
+    assert!(writer.finish().is_err());
+}
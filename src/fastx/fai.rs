@@ -0,0 +1,352 @@
+#[cfg(test)]
+use crate::args::CompressType;
+use crate::errors::AppError;
+use crate::fastx::bgzf::{
+    GziEntry, bgzf_reader_at, build_gzi, gzi_path, is_bgzf, is_gzip, read_gzi, write_gzi,
+};
+#[cfg(test)]
+use crate::fastx::writer::write_temp_file;
+use flate2::read::MultiGzDecoder;
+use rstest::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// A `.fai` line, same layout as samtools faidx.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaiRecord {
+    pub name: String,
+    pub length: u64,
+    pub offset: u64,
+    pub line_bases: u64,
+    pub line_width: u64,
+}
+
+impl FaiRecord {
+    /// Uncompressed byte offset of base `pos` (0-based).
+    fn byte_offset(&self, pos: u64) -> u64 {
+        match self.line_bases {
+            0 => self.offset,
+            line_bases => self.offset + (pos / line_bases) * self.line_width + pos % line_bases,
+        }
+    }
+
+    /// Uncompressed byte offset right after the last sequence line of the record.
+    fn end_offset(&self) -> u64 {
+        match self.line_bases {
+            0 => self.offset,
+            line_bases => {
+                let num_lines = self.length.div_ceil(line_bases);
+                self.offset + self.length + num_lines * (self.line_width - line_bases)
+            }
+        }
+    }
+}
+
+pub fn fai_path(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.fai", path.display()))
+}
+
+/// Builds the `.fai` records from an uncompressed FASTA stream.
+fn build_fai_from_reader<R: BufRead>(mut reader: R) -> Result<Vec<FaiRecord>, AppError> {
+    let mut records: Vec<FaiRecord> = Vec::new();
+
+    let mut offset: u64 = 0;
+    let mut line: Vec<u8> = Vec::new();
+
+    // Set when a sequence line is shorter than the line width, which is only valid for the last line.
+    let mut last_line = false;
+
+    loop {
+        line.clear();
+        let num_bytes = reader.read_until(b'\n', &mut line)? as u64;
+
+        if num_bytes == 0 {
+            break;
+        }
+        offset += num_bytes;
+
+        if line[0] == b'>' {
+            let header = line[1..].trim_ascii_end();
+            let name = header
+                .split(|b| b.is_ascii_whitespace())
+                .next()
+                .unwrap_or_default();
+
+            records.push(FaiRecord {
+                name: std::str::from_utf8(name)?.to_string(),
+                length: 0,
+                offset,
+                line_bases: 0,
+                line_width: 0,
+            });
+            last_line = false;
+
+            continue;
+        }
+
+        let record = records.last_mut().ok_or(AppError::InvalidIndexError(
+            "FASTA does not start with '>'".into(),
+        ))?;
+
+        let line_bases = line.trim_ascii_end().len() as u64;
+
+        if line_bases == 0 {
+            last_line = true;
+            continue;
+        }
+
+        if record.line_bases == 0 {
+            record.line_bases = line_bases;
+            record.line_width = num_bytes;
+        } else {
+            if last_line || line_bases > record.line_bases {
+                return Err(AppError::InvalidIndexError(format!(
+                    "inconsistent line length in {}",
+                    record.name
+                )));
+            }
+
+            last_line = num_bytes != record.line_width;
+        }
+
+        record.length += line_bases;
+    }
+
+    Ok(records)
+}
+
+pub fn write_fai(path: &Path, records: &[FaiRecord]) -> Result<(), AppError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    for r in records {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}",
+            r.name, r.length, r.offset, r.line_bases, r.line_width
+        )?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn read_fai(path: &Path) -> Result<Vec<FaiRecord>, AppError> {
+    let reader = BufReader::new(File::open(path)?);
+
+    let mut records: Vec<FaiRecord> = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.split('\t').collect();
+
+        if fields.len() < 5 {
+            return Err(AppError::InvalidIndexError(line));
+        }
+
+        records.push(FaiRecord {
+            name: fields[0].to_string(),
+            length: fields[1].parse()?,
+            offset: fields[2].parse()?,
+            line_bases: fields[3].parse()?,
+            line_width: fields[4].parse()?,
+        });
+    }
+
+    Ok(records)
+}
+
+/// Builds and writes `.fai` (and `.gzi` for BGZF input) next to `fasta`.
+pub fn build_index(fasta: &Path) -> Result<(), AppError> {
+    let f = File::open(fasta)?;
+
+    let records = match is_bgzf(fasta)? {
+        true => {
+            write_gzi(&gzi_path(fasta), &build_gzi(fasta)?)?;
+            build_fai_from_reader(BufReader::new(MultiGzDecoder::new(f)))?
+        }
+        false => build_fai_from_reader(BufReader::new(f))?,
+    };
+
+    write_fai(&fai_path(fasta), &records)?;
+
+    Ok(())
+}
+
+/// Random access to an indexed (plain or BGZF compressed) FASTA file.
+pub struct IndexedFasta {
+    path: PathBuf,
+    records: Vec<FaiRecord>,
+    name_to_index: HashMap<String, usize>,
+    gzi: Option<Vec<GziEntry>>,
+}
+
+impl IndexedFasta {
+    /// Opens `fasta` with its `.fai`/`.gzi`, building them first if missing.
+    pub fn open(fasta: &Path) -> Result<Self, AppError> {
+        let bgzf = is_bgzf(fasta)?;
+
+        if !bgzf && is_gzip(fasta)? {
+            return Err(AppError::InvalidIndexError(format!(
+                "{} is gzip but not BGZF compressed, recompress with bgzip",
+                fasta.display()
+            )));
+        }
+
+        let missing = !fai_path(fasta).exists() || (bgzf && !gzi_path(fasta).exists());
+        if missing {
+            build_index(fasta)?;
+        }
+
+        let records = read_fai(&fai_path(fasta))?;
+
+        let name_to_index: HashMap<String, usize> = records
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (r.name.clone(), i))
+            .collect();
+
+        let gzi = match bgzf {
+            true => Some(read_gzi(&gzi_path(fasta))?),
+            false => None,
+        };
+
+        Ok(IndexedFasta {
+            path: fasta.to_path_buf(),
+            records,
+            name_to_index,
+            gzi,
+        })
+    }
+
+    /// True if `fasta` has an index that can be used without building one.
+    pub fn has_index(fasta: &Path) -> Result<bool, AppError> {
+        if !fai_path(fasta).exists() {
+            return Ok(false);
+        }
+
+        match (is_gzip(fasta)?, is_bgzf(fasta)?) {
+            (false, _) => Ok(true),
+            (true, true) => Ok(gzi_path(fasta).exists()),
+            (true, false) => Ok(false),
+        }
+    }
+
+    pub fn records(&self) -> &[FaiRecord] {
+        &self.records
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FaiRecord> {
+        self.name_to_index.get(name).map(|i| &self.records[*i])
+    }
+
+    /// Uncompressed reader starting at `offset`. Each call opens its own file
+    /// handle, so readers can be used in parallel.
+    pub fn reader_at(&self, offset: u64) -> Result<Box<dyn Read + Send>, AppError> {
+        match &self.gzi {
+            Some(gzi) => bgzf_reader_at(&self.path, gzi, offset),
+            None => {
+                let mut f = File::open(&self.path)?;
+                f.seek(SeekFrom::Start(offset))?;
+                Ok(Box::new(BufReader::new(f)))
+            }
+        }
+    }
+
+    /// Uncompressed offset of the header of the `n`th record.
+    pub fn record_start(&self, n: usize) -> u64 {
+        match n {
+            0 => 0,
+            n => self.records[n - 1].end_offset(),
+        }
+    }
+
+    /// Sequence of `name` in the range `[start, end)` (0-based, BED style), clamped to the sequence length.
+    pub fn fetch(&self, name: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError> {
+        let record = self
+            .get(name)
+            .ok_or(AppError::SequenceNotFoundError(name.to_string()))?;
+
+        if start > end || start > record.length {
+            return Err(AppError::InvalidRangeError);
+        }
+
+        let end = end.min(record.length);
+
+        // E.g. a whole zero-length record.
+        if start == end {
+            return Ok(Vec::new());
+        }
+
+        let start_offset = record.byte_offset(start);
+        let num_bytes = record.byte_offset(end - 1) + 1 - start_offset;
+
+        let mut raw: Vec<u8> = Vec::with_capacity(num_bytes as usize);
+        self.reader_at(start_offset)?
+            .take(num_bytes)
+            .read_to_end(&mut raw)?;
+
+        raw.retain(|b| *b != b'\n' && *b != b'\r');
+
+        Ok(raw)
+    }
+}
+
+#[rstest]
+#[case(b">s1\nACGT\nAC\n", vec![FaiRecord { name: "s1".into(), length: 6, offset: 4, line_bases: 4, line_width: 5 }])]
+#[case(b">s1 desc\nACGT\n>s2\nAC\nAC\n", vec![
+    FaiRecord { name: "s1".into(), length: 4, offset: 9, line_bases: 4, line_width: 5 },
+    FaiRecord { name: "s2".into(), length: 4, offset: 18, line_bases: 2, line_width: 3 },
+])]
+#[case(b">s1\r\nACG\r\nA\r\n", vec![FaiRecord { name: "s1".into(), length: 4, offset: 5, line_bases: 3, line_width: 5 }])]
+
+fn test_build_fai(#[case] fasta: &[u8], #[case] expected: Vec<FaiRecord>) {
+    assert_eq!(build_fai_from_reader(fasta).unwrap(), expected);
+}
+
+#[rstest]
+#[case(b">s1\nAC\nACGT\n")]
+#[case(b">s1\nACGT\nAC\nAC\n")]
+
+fn test_build_fai_inconsistent(#[case] fasta: &[u8]) {
+    assert!(build_fai_from_reader(fasta).is_err());
+}
+
+#[rstest]
+#[case(None, "s1", 0, 8, Some(b"ACGTACGT".to_vec()))]
+#[case(None, "s1", 5, 100, Some(b"CGT".to_vec()))]
+#[case(None, "s1", 3, 3, Some(vec![]))]
+#[case(None, "empty", 0, 0, Some(vec![]))]
+#[case(None, "s1", 9, 10, None)]
+#[case(None, "s1", 5, 4, None)]
+#[case(Some(CompressType::Bgzip), "s1", 2, 7, Some(b"GTACG".to_vec()))]
+#[case(Some(CompressType::Bgzip), "empty", 0, 0, Some(vec![]))]
+
+fn test_fetch(
+    #[case] compress: Option<CompressType>,
+    #[case] name: &str,
+    #[case] start: u64,
+    #[case] end: u64,
+    #[case] expected: Option<Vec<u8>>,
+) {
+    let (_dir, path) = write_temp_file("test.fasta", compress, |writer| {
+        writer.write_all(b">empty\n>s1\nACGTAC\nGT\n")?;
+        Ok(())
+    });
+
+    let index = IndexedFasta::open(&path).unwrap();
+
+    assert_eq!(index.fetch(name, start, end).ok(), expected);
+}
+
+#[rstest]
+
+fn test_read_fai_invalid_utf8() {
+    let (_dir, path) = write_temp_file("test.fasta.fai", None, |writer| {
+        writer.write_all(b"s1\t4\t4\t4\t5\n\xff\t4\t13\t4\t5\n")?;
+        Ok(())
+    });
+
+    assert!(read_fai(&path).is_err());
+}
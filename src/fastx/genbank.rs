@@ -0,0 +1,485 @@
+use crate::errors::AppError;
+use crate::iupac::reverse_complement;
+use rstest::*;
+use std::io::BufRead;
+
+/// Feature location, converted to 0-based half open (BED style) coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    Range { start: usize, end: usize },
+    Complement(Box<Location>),
+    Join(Vec<Location>),
+}
+
+/// Splits `s` on commas that are not nested inside parentheses.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut depth: usize = 0;
+    let mut last: usize = 0;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&s[last..i]);
+                last = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[last..]);
+
+    parts
+}
+
+fn parse_position(position: &str) -> Result<usize, AppError> {
+    position
+        .trim_matches(|c| c == '<' || c == '>')
+        .parse::<usize>()
+        .map_err(|_| AppError::FlatFileParseError(format!("invalid position: {position}")))
+}
+
+impl Location {
+    /// Parses an INSDC location such as `complement(join(1..10,20..>30))`.
+    pub fn parse(location: &str) -> Result<Self, AppError> {
+        let location: String = location.chars().filter(|c| !c.is_whitespace()).collect();
+        let location = location.as_str();
+
+        if let Some(inner) = location
+            .strip_prefix("complement(")
+            .and_then(|l| l.strip_suffix(')'))
+        {
+            return Ok(Location::Complement(Box::new(Location::parse(inner)?)));
+        }
+
+        if let Some(inner) = location
+            .strip_prefix("join(")
+            .or_else(|| location.strip_prefix("order("))
+            .and_then(|l| l.strip_suffix(')'))
+        {
+            let parts = split_top_level(inner)
+                .into_iter()
+                .map(Location::parse)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            return Ok(Location::Join(parts));
+        }
+
+        if location.contains(':') {
+            return Err(AppError::FlatFileParseError(format!(
+                "remote locations are not supported: {location}"
+            )));
+        }
+
+        // Site between two bases, e.g. 12^13.
+        if let Some((start, _)) = location.split_once('^') {
+            let start = parse_position(start)?;
+            return Ok(Location::Range { start, end: start });
+        }
+
+        match location.split_once("..") {
+            Some((start, end)) => Ok(Location::Range {
+                start: parse_position(start)?.saturating_sub(1),
+                end: parse_position(end)?,
+            }),
+            None => {
+                // Single base, or one base within a range (e.g. 102.110).
+                let position = parse_position(location.split('.').next().unwrap_or_default())?;
+                Ok(Location::Range {
+                    start: position.saturating_sub(1),
+                    end: position,
+                })
+            }
+        }
+    }
+
+    /// Feature sequence, in the orientation of the feature.
+    pub fn extract(&self, seq: &[u8]) -> Vec<u8> {
+        match self {
+            Location::Range { start, end } => {
+                let end = (*end).min(seq.len());
+                let start = (*start).min(end);
+                seq[start..end].to_vec()
+            }
+            Location::Complement(inner) => reverse_complement(&inner.extract(seq)),
+            Location::Join(parts) => parts.iter().flat_map(|p| p.extract(seq)).collect(),
+        }
+    }
+
+    /// Leftmost start and rightmost end of the location.
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            Location::Range { start, end } => (*start, *end),
+            Location::Complement(inner) => inner.span(),
+            Location::Join(parts) => parts
+                .iter()
+                .map(Location::span)
+                .fold((usize::MAX, 0), |(min_start, max_end), (start, end)| {
+                    (min_start.min(start), max_end.max(end))
+                }),
+        }
+    }
+
+    /// True if the feature is on the reverse strand.
+    pub fn is_complement(&self) -> bool {
+        match self {
+            Location::Range { .. } => false,
+            Location::Complement(_) => true,
+            Location::Join(parts) => !parts.is_empty() && parts.iter().all(Location::is_complement),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Feature {
+    pub kind: String,
+    pub location: String,
+    pub qualifiers: Vec<(String, String)>,
+}
+
+impl Feature {
+    pub fn qualifier(&self, key: &str) -> Option<&str> {
+        self.qualifiers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FlatFileRecord {
+    pub id: String,
+    pub definition: Option<String>,
+    pub features: Vec<Feature>,
+    pub seq: Vec<u8>,
+}
+
+impl FlatFileRecord {
+    /// FASTA header, with the definition line as description.
+    pub fn header(&self) -> String {
+        match &self.definition {
+            Some(definition) => format!("{} {definition}", self.id),
+            None => self.id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Section {
+    Header,
+    Definition,
+    Features,
+    Sequence,
+}
+
+/// True if the first line looks like a GenBank (`LOCUS`) or EMBL (`ID`) flat file.
+pub fn is_flat_file(buf: &[u8]) -> bool {
+    let first_line = buf.trim_ascii_start();
+    first_line.starts_with(b"LOCUS ") || first_line.starts_with(b"ID   ")
+}
+
+/// GenBank keyword starting at column 0, or EMBL line code in columns 0-1 followed by
+/// spaces. Indented lines (e.g. qualifier continuations) have no code.
+fn line_code(line: &str) -> &str {
+    match line.starts_with(|c: char| c.is_ascii_whitespace()) {
+        true => "",
+        false => line.split_whitespace().next().unwrap_or_default(),
+    }
+}
+
+/// Streaming GenBank and EMBL parser, yielding one record at a time.
+pub struct FlatFileReader<R: BufRead> {
+    reader: R,
+    line: String,
+}
+
+impl<R: BufRead> FlatFileReader<R> {
+    pub fn new(reader: R) -> Self {
+        FlatFileReader {
+            reader,
+            line: String::new(),
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Option<FlatFileRecord>, AppError> {
+        let mut record = FlatFileRecord::default();
+        let mut version: Option<String> = None;
+        let mut section = Section::Header;
+        let mut has_content = false;
+
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                break;
+            }
+
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            has_content = true;
+
+            if line.starts_with("//") {
+                break;
+            }
+
+            let code = line_code(line);
+            let value = line.get(12..).unwrap_or_default().trim();
+
+            match code {
+                // GenBank.
+                "LOCUS" => {
+                    record.id = line.split_whitespace().nth(1).unwrap_or_default().into();
+                    section = Section::Header;
+                }
+                "DEFINITION" => {
+                    record.definition = Some(value.to_string());
+                    section = Section::Definition;
+                }
+                "VERSION" => {
+                    version = line.split_whitespace().nth(1).map(str::to_string);
+                    section = Section::Header;
+                }
+                "FEATURES" => section = Section::Features,
+                "ORIGIN" => section = Section::Sequence,
+                // EMBL.
+                "ID" => {
+                    let id = line[2..].split(';').next().unwrap_or_default();
+                    record.id = id.trim().to_string();
+                }
+                "DE" => {
+                    let desc = line[2..].trim();
+                    record.definition = match record.definition.take() {
+                        Some(definition) => Some(format!("{definition} {desc}")),
+                        None => Some(desc.to_string()),
+                    };
+                }
+                "FT" => parse_feature_line(&format!("  {}", &line[2..]), &mut record.features),
+                "SQ" => section = Section::Sequence,
+                _ => match section {
+                    Section::Sequence => record.seq.extend(
+                        line.bytes()
+                            .filter(u8::is_ascii_alphabetic)
+                            .map(|b| b.to_ascii_uppercase()),
+                    ),
+                    Section::Features if line.starts_with(' ') => {
+                        parse_feature_line(line, &mut record.features)
+                    }
+                    Section::Definition if line.starts_with(' ') => {
+                        if let Some(definition) = record.definition.as_mut() {
+                            definition.push(' ');
+                            definition.push_str(line.trim());
+                        }
+                    }
+                    _ => section = Section::Header,
+                },
+            }
+        }
+
+        if !has_content {
+            return Ok(None);
+        }
+
+        if let Some(version) = version {
+            record.id = version;
+        }
+
+        record.definition = record
+            .definition
+            .map(|d| d.trim_end_matches('.').to_string());
+
+        for feature in record.features.iter_mut() {
+            for (_, value) in feature.qualifiers.iter_mut() {
+                *value = value.trim_matches('"').to_string();
+            }
+        }
+
+        Ok(Some(record))
+    }
+}
+
+/// Feature table line, where the feature key starts at column 5 and the location
+/// or qualifiers at column 21.
+fn parse_feature_line(line: &str, features: &mut Vec<Feature>) {
+    let key = line.get(5..21).unwrap_or_default().trim();
+    let value = line.get(21..).unwrap_or_default().trim();
+
+    if !key.is_empty() {
+        features.push(Feature {
+            kind: key.to_string(),
+            location: value.to_string(),
+            qualifiers: Vec::new(),
+        });
+        return;
+    }
+
+    let Some(feature) = features.last_mut() else {
+        return;
+    };
+
+    if let Some(qualifier) = value.strip_prefix('/') {
+        let (k, v) = qualifier.split_once('=').unwrap_or((qualifier, ""));
+        feature.qualifiers.push((k.to_string(), v.to_string()));
+        return;
+    }
+
+    // Continuation of a multi-line location or qualifier.
+    match feature.qualifiers.last_mut() {
+        Some((k, v)) => {
+            if k != "translation" {
+                v.push(' ');
+            }
+            v.push_str(value);
+        }
+        None => feature.location.push_str(value),
+    }
+}
+
+impl<R: BufRead> Iterator for FlatFileReader<R> {
+    type Item = Result<FlatFileRecord, AppError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[rstest]
+#[case("10..20", Location::Range { start: 9, end: 20 })]
+#[case("<1..>20", Location::Range { start: 0, end: 20 })]
+#[case("5", Location::Range { start: 4, end: 5 })]
+#[case("complement(3..5)", Location::Complement(Box::new(Location::Range { start: 2, end: 5 })))]
+#[case("join(1..2, 4..5)", Location::Join(vec![Location::Range { start: 0, end: 2 }, Location::Range { start: 3, end: 5 }]))]
+#[case("complement(join(1..2,4..5))", Location::Complement(Box::new(Location::Join(vec![Location::Range { start: 0, end: 2 }, Location::Range { start: 3, end: 5 }]))))]
+
+fn test_parse_location(#[case] location: &str, #[case] expected: Location) {
+    assert_eq!(Location::parse(location).unwrap(), expected);
+}
+
+#[rstest]
+#[case("1..4", b"ACGT")]
+#[case("complement(1..3)", b"CGT")]
+#[case("join(1..2,7..8)", b"ACTT")]
+#[case("complement(join(1..2,7..8))", b"AAGT")]
+#[case("join(complement(7..8),complement(1..2))", b"AAGT")]
+// Ambiguity codes and softmasking.
+#[case("complement(9..14)", b"gyRKNA")]
+
+fn test_extract_location(#[case] location: &str, #[case] expected: &[u8]) {
+    let seq = b"ACGTGGTTTNMYrc";
+    assert_eq!(
+        &Location::parse(location).unwrap().extract(seq)[..],
+        expected
+    );
+}
+
+#[rstest]
+#[case(
+    "LOCUS       TEST    12 bp    DNA     linear   BCT 01-JAN-2000
+DEFINITION  Test sequence,
+            second line.
+VERSION     TEST.1
+FEATURES             Location/Qualifiers
+     CDS             complement(join(1..3,
+                     7..9))
+                     /locus_tag=\"T_0001\"
+                     /product=\"hypothetical
+                     protein\"
+ORIGIN
+        1 acgtac gtacgt
+//
+",
+    "TEST.1",
+    "Test sequence, second line",
+    b"ACGTACGTACGT",
+    "complement(join(1..3,7..9))",
+    "hypothetical protein"
+)]
+#[case(
+    "ID   X56734; SV 1; linear; mRNA; STD; PLN; 12 BP.
+DE   Test sequence
+FT   rRNA            2..5
+FT                   /product=\"16S\"
+SQ   Sequence 12 BP;
+     acgtacgtac gt                                                        12
+//
+",
+    "X56734",
+    "Test sequence",
+    b"ACGTACGTACGT",
+    "2..5",
+    "16S"
+)]
+
+fn test_flat_file_reader(
+    #[case] flat_file: &str,
+    #[case] expected_id: &str,
+    #[case] expected_definition: &str,
+    #[case] expected_seq: &[u8],
+    #[case] expected_location: &str,
+    #[case] expected_product: &str,
+) {
+    let records: Vec<FlatFileRecord> = FlatFileReader::new(flat_file.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(records.len(), 1);
+
+    let record = &records[0];
+    assert_eq!(record.id, expected_id);
+    assert_eq!(record.definition.as_deref(), Some(expected_definition));
+    assert_eq!(&record.seq[..], expected_seq);
+    assert_eq!(record.features[0].location, expected_location);
+    assert_eq!(
+        record.features[0].qualifier("product"),
+        Some(expected_product)
+    );
+}
+
+#[rstest]
+#[case(
+    "LOCUS       TEST    8 bp    DNA     linear   BCT 01-JAN-2000
+VERSION     TEST.1
+FEATURES             Location/Qualifiers
+     gene            1..8
+                     /note=\"first line
+                     ID unknown
+                     VERSION 2
+                     ORIGIN\"
+ORIGIN
+        1 acgtacgt
+//
+",
+    "TEST.1",
+    "first line ID unknown VERSION 2 ORIGIN"
+)]
+#[case(
+    "ID   X56734; SV 1; linear; mRNA; STD; PLN; 8 BP.
+FT   gene            1..8
+FT                   /note=\"first line
+FT                   ID unknown\"
+SQ   Sequence 8 BP;
+     acgtacgt                                                              8
+//
+",
+    "X56734",
+    "first line ID unknown"
+)]
+
+fn test_flat_file_keyword_continuation(
+    #[case] flat_file: &str,
+    #[case] expected_id: &str,
+    #[case] expected_note: &str,
+) {
+    let records: Vec<FlatFileRecord> = FlatFileReader::new(flat_file.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].id, expected_id);
+    assert_eq!(&records[0].seq[..], b"ACGTACGT");
+    assert_eq!(
+        records[0].features[0].qualifier("note"),
+        Some(expected_note)
+    );
+}
@@ -0,0 +1,51 @@
+use rstest::*;
+
+/// FASTA/FASTQ header split into id and (optional) description.
+///
+/// The id is everything up to the first whitespace, the description is the remainder.
+#[derive(Debug, PartialEq)]
+pub struct Header<'a> {
+    pub id: &'a [u8],
+    pub desc: Option<&'a [u8]>,
+}
+
+impl<'a> Header<'a> {
+    pub fn parse(header: &'a [u8]) -> Self {
+        match header.iter().position(|b| b.is_ascii_whitespace()) {
+            Some(i) => {
+                let desc = header[i + 1..].trim_ascii();
+
+                Header {
+                    id: &header[..i],
+                    desc: (!desc.is_empty()).then_some(desc),
+                }
+            }
+            None => Header {
+                id: header,
+                desc: None,
+            },
+        }
+    }
+
+    /// Same description, different id. Used when subcommands tag the id (e.g. `id|start-end`).
+    pub fn with_id<'b>(&self, id: &'b [u8]) -> Header<'b>
+    where
+        'a: 'b,
+    {
+        Header {
+            id,
+            desc: self.desc,
+        }
+    }
+}
+
+#[rstest]
+#[case(b"", Header { id: b"", desc: None })]
+#[case(b"seq_1", Header { id: b"seq_1", desc: None })]
+#[case(b"seq_1 ", Header { id: b"seq_1", desc: None })]
+#[case(b"seq_1 some description", Header { id: b"seq_1", desc: Some(b"some description") })]
+#[case(b"seq_1\tdesc", Header { id: b"seq_1", desc: Some(b"desc") })]
+
+fn test_parse_header(#[case] header: &[u8], #[case] expected: Header) {
+    assert_eq!(Header::parse(header), expected);
+}
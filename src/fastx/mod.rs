@@ -0,0 +1,29 @@
+pub mod batch;
+pub use batch::for_each_batch;
+
+pub mod bgzf;
+
+pub mod compression;
+
+pub mod fai;
+pub use fai::IndexedFasta;
+
+pub mod genbank;
+
+pub mod header;
+pub use header::Header;
+
+pub mod random_access;
+pub use random_access::open_random_access;
+
+pub mod reader;
+pub use reader::fastx_reader;
+
+pub mod record;
+pub use record::{OwnedRecord, format_extension, read_records};
+
+pub mod twobit;
+pub use twobit::TwoBitFile;
+
+pub mod writer;
+pub use writer::{FastxWriter, OutputWriter, WriterOpts, get_writer};
@@ -0,0 +1,30 @@
+use crate::errors::AppError;
+use crate::fastx::IndexedFasta;
+use crate::fastx::twobit::TwoBitFile;
+use std::path::Path;
+
+/// Region queries on an indexed FASTA or a 2bit file.
+pub trait RandomAccess: Sync {
+    /// Sequence of `name` in the range `[start, end)` (0-based, BED style), clamped to the sequence length.
+    fn fetch(&self, name: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError>;
+}
+
+impl RandomAccess for IndexedFasta {
+    fn fetch(&self, name: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError> {
+        IndexedFasta::fetch(self, name, start, end)
+    }
+}
+
+impl RandomAccess for TwoBitFile {
+    fn fetch(&self, name: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError> {
+        TwoBitFile::fetch(self, name, start, end)
+    }
+}
+
+/// Opens `path` as 2bit if it has the 2bit signature, otherwise as an indexed FASTA.
+pub fn open_random_access(path: &Path) -> Result<Box<dyn RandomAccess>, AppError> {
+    match TwoBitFile::is_twobit(path)? {
+        true => Ok(Box::new(TwoBitFile::open(path)?)),
+        false => Ok(Box::new(IndexedFasta::open(path)?)),
+    }
+}
@@ -0,0 +1,150 @@
+#[cfg(test)]
+use crate::args::CompressType;
+use crate::errors::AppError;
+use crate::fastx::genbank::{FlatFileReader, is_flat_file};
+use crate::fastx::twobit::TwoBitFile;
+#[cfg(test)]
+use crate::fastx::writer::write_temp_file;
+use bio_utils_rs::errors::BioError;
+use flate2::read::MultiGzDecoder;
+use liblzma::read::XzDecoder;
+use needletail::{FastxReader, parse_fastx_reader};
+use rstest::*;
+use std::fs::File;
+#[cfg(test)]
+use std::io::Write;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Buffered reader over a file or stdin, decoding any compression written by `get_writer`.
+pub fn open_input(path: Option<&Path>) -> Result<Box<dyn BufRead + Send>, AppError> {
+    let raw: Box<dyn Read + Send> = match path {
+        Some(path) => Box::new(File::open(path)?),
+        None => Box::new(std::io::stdin()),
+    };
+
+    decode(BufReader::new(raw))
+}
+
+/// Decodes gzip (including BGZF), zstd or xz data, detected from its magic bytes.
+fn decode<R: BufRead + Send + 'static>(mut reader: R) -> Result<Box<dyn BufRead + Send>, AppError> {
+    let magic = reader.fill_buf()?;
+
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Ok(Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(reader)?,
+        )))
+    } else if magic.starts_with(&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00]) {
+        Ok(Box::new(BufReader::new(XzDecoder::new_multi_decoder(
+            reader,
+        ))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Presents `(header, seq)` records from other formats as a FASTA stream,
+/// so that they can be read by needletail.
+pub struct FastaStream<I> {
+    records: I,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<I> FastaStream<I> {
+    pub fn new(records: I) -> Self {
+        FastaStream {
+            records,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<(String, Vec<u8>), AppError>>> Read for FastaStream<I> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            let (header, seq) = match self.records.next() {
+                Some(record) => record.map_err(|e| std::io::Error::other(e.to_string()))?,
+                None => return Ok(0),
+            };
+
+            self.buffer.clear();
+            self.position = 0;
+
+            self.buffer.push(b'>');
+            self.buffer.extend_from_slice(header.as_bytes());
+            self.buffer.push(b'\n');
+            self.buffer.extend_from_slice(&seq);
+            self.buffer.push(b'\n');
+        }
+
+        let n = buf.len().min(self.buffer.len() - self.position);
+        buf[..n].copy_from_slice(&self.buffer[self.position..self.position + n]);
+        self.position += n;
+
+        Ok(n)
+    }
+}
+
+fn flat_file_reader(input: Box<dyn BufRead + Send>) -> Result<Box<dyn FastxReader>, AppError> {
+    let records = FlatFileReader::new(input).map(|r| r.map(|r| (r.header(), r.seq)));
+    Ok(parse_fastx_reader(FastaStream::new(records))?)
+}
+
+/// FASTA/FASTQ reader that also accepts GenBank and EMBL flat files and 2bit files,
+/// which are presented as FASTA records.
+pub fn fastx_reader(path: Option<PathBuf>) -> Result<Box<dyn FastxReader>, AppError> {
+    if let Some(path) = &path {
+        if !path.exists() {
+            return Err(BioError::FileDoesNotExistError(path.display().to_string()).into());
+        }
+
+        if TwoBitFile::is_twobit(path)? {
+            let records = TwoBitFile::open(path)?.sequences();
+            return Ok(parse_fastx_reader(FastaStream::new(records))?);
+        }
+    }
+
+    // Compression is detected from the content, so any output of `get_writer` can be read back.
+    let mut input = open_input(path.as_deref())?;
+
+    match is_flat_file(input.fill_buf()?) {
+        true => flat_file_reader(input),
+        false => Ok(parse_fastx_reader(input)?),
+    }
+}
+
+#[rstest]
+#[case("out.fa", None)]
+#[case("out.fa", Some(CompressType::Gzip))]
+#[case("out.fa.gz", None)]
+#[case("out.fa.bgz", None)]
+#[case("out.fa.zst", None)]
+#[case("out.fa.xz", None)]
+#[case("out.fasta", Some(CompressType::Zstd))]
+
+fn test_fastx_reader_compressed(#[case] name: &str, #[case] compress: Option<CompressType>) {
+    let (_dir, path) = write_temp_file(name, compress, |writer| {
+        writer.write_all(b">seq_1 desc\nACGT\n>seq_2\nTTGCA\n")?;
+        Ok(())
+    });
+
+    let mut reader = fastx_reader(Some(path)).unwrap();
+    let mut records: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+
+    while let Some(record) = reader.next() {
+        let record = record.unwrap();
+        records.push((record.id().to_vec(), record.seq().to_vec()));
+    }
+
+    assert_eq!(
+        records,
+        vec![
+            (b"seq_1 desc".to_vec(), b"ACGT".to_vec()),
+            (b"seq_2".to_vec(), b"TTGCA".to_vec()),
+        ]
+    );
+}
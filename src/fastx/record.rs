@@ -0,0 +1,58 @@
+use crate::errors::AppError;
+use crate::fastx::{Header, fastx_reader};
+use needletail::parser::{Format, SequenceRecord};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// File extension for output files named by the subcommand itself.
+pub fn format_extension(format: Format) -> &'static str {
+    match format {
+        Format::Fasta => "fasta",
+        Format::Fastq => "fastq",
+    }
+}
+
+/// Owned FASTA/FASTQ record, for subcommands that need to keep records in memory.
+#[derive(Debug, Serialize)]
+pub struct OwnedRecord {
+    pub header: Vec<u8>,
+    pub seq: Vec<u8>,
+    pub qual: Option<Vec<u8>>,
+}
+
+impl OwnedRecord {
+    pub fn header(&self) -> Header<'_> {
+        Header::parse(&self.header)
+    }
+
+    pub fn qual(&self) -> Option<&[u8]> {
+        self.qual.as_deref()
+    }
+}
+
+impl From<&SequenceRecord<'_>> for OwnedRecord {
+    fn from(record: &SequenceRecord) -> Self {
+        OwnedRecord {
+            header: record.id().to_vec(),
+            seq: record.seq().into_owned(),
+            qual: record.qual().map(|q| q.to_vec()),
+        }
+    }
+}
+
+/// Reads all valid records into memory.
+pub fn read_records(fasta: Option<PathBuf>) -> Result<Vec<OwnedRecord>, AppError> {
+    let mut reader = fastx_reader(fasta)?;
+
+    let mut records: Vec<OwnedRecord> = Vec::new();
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        records.push(OwnedRecord::from(&record));
+    }
+
+    Ok(records)
+}
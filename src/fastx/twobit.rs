@@ -0,0 +1,406 @@
+use crate::errors::AppError;
+#[cfg(test)]
+use crate::fastx::writer::write_temp_file;
+use rstest::*;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const TWOBIT_SIGNATURE: u32 = 0x1A41_2743;
+
+/// Bases in 2bit order, i.e., T=0, C=1, A=2, G=3.
+const TWOBIT_BASES: [u8; 4] = [b'T', b'C', b'A', b'G'];
+
+fn base_to_bits(base: u8) -> u8 {
+    match base.to_ascii_uppercase() {
+        b'C' => 1,
+        b'A' => 2,
+        b'G' => 3,
+        _ => 0,
+    }
+}
+
+/// `(start, size)` of each run of bases matching `predicate`.
+fn blocks(seq: &[u8], predicate: impl Fn(u8) -> bool) -> Vec<(u32, u32)> {
+    let mut blocks: Vec<(u32, u32)> = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (i, base) in seq.iter().enumerate() {
+        match (predicate(*base), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                blocks.push((s as u32, (i - s) as u32));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(s) = start {
+        blocks.push((s as u32, (seq.len() - s) as u32));
+    }
+
+    blocks
+}
+
+/// A sequence in 2bit encoding. Bases other than ACGT are stored as N blocks and
+/// lowercase bases as mask blocks.
+#[derive(Debug)]
+pub struct TwoBitRecord {
+    pub name: String,
+    pub dna_size: u32,
+    pub n_blocks: Vec<(u32, u32)>,
+    pub mask_blocks: Vec<(u32, u32)>,
+    pub packed: Vec<u8>,
+}
+
+impl TwoBitRecord {
+    pub fn encode(name: &str, seq: &[u8]) -> Result<Self, AppError> {
+        if name.len() > u8::MAX as usize {
+            return Err(AppError::InvalidArgError(format!(
+                "2bit names are limited to 255 characters: {name}"
+            )));
+        }
+
+        let dna_size = u32::try_from(seq.len()).map_err(|_| {
+            AppError::InvalidArgError(format!("{name} is too long for the 2bit format"))
+        })?;
+
+        let packed = seq
+            .chunks(4)
+            .map(|chunk| {
+                chunk.iter().enumerate().fold(0u8, |byte, (i, base)| {
+                    byte | (base_to_bits(*base) << (6 - 2 * i))
+                })
+            })
+            .collect();
+
+        Ok(TwoBitRecord {
+            name: name.to_string(),
+            dna_size,
+            n_blocks: blocks(seq, |b| {
+                !matches!(b.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T')
+            }),
+            mask_blocks: blocks(seq, |b| b.is_ascii_lowercase()),
+            packed,
+        })
+    }
+
+    fn num_bytes(&self) -> u64 {
+        (4 + 4 + 8 * self.n_blocks.len() + 4 + 8 * self.mask_blocks.len() + 4 + self.packed.len())
+            as u64
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), AppError> {
+        writer.write_all(&self.dna_size.to_le_bytes())?;
+
+        for blocks in [&self.n_blocks, &self.mask_blocks] {
+            writer.write_all(&(blocks.len() as u32).to_le_bytes())?;
+            for (start, _) in blocks.iter() {
+                writer.write_all(&start.to_le_bytes())?;
+            }
+            for (_, size) in blocks.iter() {
+                writer.write_all(&size.to_le_bytes())?;
+            }
+        }
+
+        // Reserved.
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&self.packed)?;
+
+        Ok(())
+    }
+}
+
+/// Writes a 2bit file. Version 1 (64 bit offsets) is only used if the file exceeds 4 GiB.
+pub fn write_twobit<W: Write>(writer: &mut W, records: &[TwoBitRecord]) -> Result<(), AppError> {
+    let records_size: u64 = records.iter().map(TwoBitRecord::num_bytes).sum();
+    let index_size = |offset_size: u64| -> u64 {
+        records
+            .iter()
+            .map(|r| 1 + r.name.len() as u64 + offset_size)
+            .sum()
+    };
+
+    let version: u32 = match 16 + index_size(4) + records_size > u32::MAX as u64 {
+        true => 1,
+        false => 0,
+    };
+    let offset_size: u64 = if version == 1 { 8 } else { 4 };
+
+    writer.write_all(&TWOBIT_SIGNATURE.to_le_bytes())?;
+    writer.write_all(&version.to_le_bytes())?;
+    writer.write_all(&(records.len() as u32).to_le_bytes())?;
+    writer.write_all(&0u32.to_le_bytes())?;
+
+    let mut offset = 16 + index_size(offset_size);
+    for record in records {
+        writer.write_all(&[record.name.len() as u8])?;
+        writer.write_all(record.name.as_bytes())?;
+
+        match version {
+            1 => writer.write_all(&offset.to_le_bytes())?,
+            _ => writer.write_all(&(offset as u32).to_le_bytes())?,
+        }
+
+        offset += record.num_bytes();
+    }
+
+    for record in records {
+        record.write(writer)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+struct SeqHeader {
+    dna_size: u64,
+    n_blocks: Vec<(u64, u64)>,
+    mask_blocks: Vec<(u64, u64)>,
+    dna_offset: u64,
+}
+
+/// Random access to a 2bit file. Sequence headers are read on demand, so opening
+/// only reads the index.
+pub struct TwoBitFile {
+    path: PathBuf,
+    big_endian: bool,
+    names: Vec<String>,
+    offsets: Vec<u64>,
+    name_to_index: HashMap<String, usize>,
+}
+
+fn read_u32<R: Read>(reader: &mut R, big_endian: bool) -> Result<u32, AppError> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+
+    match big_endian {
+        true => Ok(u32::from_be_bytes(buf)),
+        false => Ok(u32::from_le_bytes(buf)),
+    }
+}
+
+fn read_u64<R: Read>(reader: &mut R, big_endian: bool) -> Result<u64, AppError> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+
+    match big_endian {
+        true => Ok(u64::from_be_bytes(buf)),
+        false => Ok(u64::from_le_bytes(buf)),
+    }
+}
+
+impl TwoBitFile {
+    /// True if `path` starts with the 2bit signature, in either byte order.
+    pub fn is_twobit(path: &Path) -> Result<bool, AppError> {
+        let mut buf = [0u8; 4];
+
+        match File::open(path)?.read_exact(&mut buf) {
+            Ok(_) => Ok(u32::from_le_bytes(buf) == TWOBIT_SIGNATURE
+                || u32::from_be_bytes(buf) == TWOBIT_SIGNATURE),
+            Err(_) => Ok(false),
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let big_endian = match read_u32(&mut reader, false)? {
+            TWOBIT_SIGNATURE => false,
+            signature if signature.swap_bytes() == TWOBIT_SIGNATURE => true,
+            _ => {
+                return Err(AppError::InvalidIndexError(format!(
+                    "{} is not a 2bit file",
+                    path.display()
+                )));
+            }
+        };
+
+        let version = read_u32(&mut reader, big_endian)?;
+        let num_seqs = read_u32(&mut reader, big_endian)? as usize;
+        let _reserved = read_u32(&mut reader, big_endian)?;
+
+        let mut names: Vec<String> = Vec::with_capacity(num_seqs);
+        let mut offsets: Vec<u64> = Vec::with_capacity(num_seqs);
+
+        for _ in 0..num_seqs {
+            let mut name_size = [0u8; 1];
+            reader.read_exact(&mut name_size)?;
+
+            let mut name = vec![0u8; name_size[0] as usize];
+            reader.read_exact(&mut name)?;
+            names.push(String::from_utf8_lossy(&name).into_owned());
+
+            offsets.push(match version {
+                0 => read_u32(&mut reader, big_endian)? as u64,
+                _ => read_u64(&mut reader, big_endian)?,
+            });
+        }
+
+        let name_to_index = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i))
+            .collect();
+
+        Ok(TwoBitFile {
+            path: path.to_path_buf(),
+            big_endian,
+            names,
+            offsets,
+            name_to_index,
+        })
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    fn read_blocks<R: Read>(&self, reader: &mut R) -> Result<Vec<(u64, u64)>, AppError> {
+        let num_blocks = read_u32(reader, self.big_endian)? as usize;
+
+        let starts = (0..num_blocks)
+            .map(|_| read_u32(reader, self.big_endian))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sizes = (0..num_blocks)
+            .map(|_| read_u32(reader, self.big_endian))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(starts
+            .into_iter()
+            .zip(sizes)
+            .map(|(start, size)| (start as u64, size as u64))
+            .collect())
+    }
+
+    fn seq_header(&self, name: &str) -> Result<SeqHeader, AppError> {
+        let index = *self
+            .name_to_index
+            .get(name)
+            .ok_or(AppError::SequenceNotFoundError(name.to_string()))?;
+
+        let mut f = File::open(&self.path)?;
+        f.seek(SeekFrom::Start(self.offsets[index]))?;
+        let mut reader = BufReader::new(f);
+
+        let dna_size = read_u32(&mut reader, self.big_endian)? as u64;
+        let n_blocks = self.read_blocks(&mut reader)?;
+        let mask_blocks = self.read_blocks(&mut reader)?;
+        let _reserved = read_u32(&mut reader, self.big_endian)?;
+
+        let dna_offset = self.offsets[index]
+            + 4
+            + 4
+            + 8 * n_blocks.len() as u64
+            + 4
+            + 8 * mask_blocks.len() as u64
+            + 4;
+
+        Ok(SeqHeader {
+            dna_size,
+            n_blocks,
+            mask_blocks,
+            dna_offset,
+        })
+    }
+
+    pub fn length(&self, name: &str) -> Result<u64, AppError> {
+        Ok(self.seq_header(name)?.dna_size)
+    }
+
+    /// Sequence of `name` in the range `[start, end)` (0-based, BED style), clamped to the
+    /// sequence length. N blocks and softmasking are restored.
+    pub fn fetch(&self, name: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError> {
+        let header = self.seq_header(name)?;
+
+        let end = end.min(header.dna_size);
+        if start >= end {
+            return Err(AppError::InvalidRangeError);
+        }
+
+        let first_byte = start / 4;
+        let mut packed = vec![0u8; ((end - 1) / 4 - first_byte + 1) as usize];
+
+        let mut f = File::open(&self.path)?;
+        f.seek(SeekFrom::Start(header.dna_offset + first_byte))?;
+        f.read_exact(&mut packed)?;
+
+        let mut seq: Vec<u8> = (start..end)
+            .map(|pos| {
+                let byte = packed[(pos / 4 - first_byte) as usize];
+                TWOBIT_BASES[((byte >> (6 - 2 * (pos % 4))) & 0b11) as usize]
+            })
+            .collect();
+
+        let overlapping = |blocks: &[(u64, u64)]| -> Vec<std::ops::Range<usize>> {
+            blocks
+                .iter()
+                .filter(|(s, size)| *s < end && s + size > start)
+                .map(|(s, size)| {
+                    ((*s).max(start) - start) as usize..((s + size).min(end) - start) as usize
+                })
+                .collect()
+        };
+
+        for range in overlapping(&header.n_blocks) {
+            seq[range].fill(b'N');
+        }
+
+        for range in overlapping(&header.mask_blocks) {
+            seq[range].make_ascii_lowercase();
+        }
+
+        Ok(seq)
+    }
+
+    /// All sequences as `(name, seq)`, in file order.
+    pub fn sequences(self) -> impl Iterator<Item = Result<(String, Vec<u8>), AppError>> + Send {
+        (0..self.names.len()).map(move |i| {
+            let name = &self.names[i];
+            match self.fetch(name, 0, u64::MAX) {
+                Ok(seq) => Ok((name.clone(), seq)),
+                Err(AppError::InvalidRangeError) => Ok((name.clone(), Vec::new())),
+                Err(e) => Err(e),
+            }
+        })
+    }
+}
+
+#[rstest]
+#[case(b"ACGT", vec![], vec![])]
+#[case(b"NNACgtNA", vec![(0, 2), (6, 1)], vec![(4, 2)])]
+#[case(b"acRYn", vec![(2, 3)], vec![(0, 2), (4, 1)])]
+
+fn test_encode_blocks(
+    #[case] seq: &[u8],
+    #[case] expected_n_blocks: Vec<(u32, u32)>,
+    #[case] expected_mask_blocks: Vec<(u32, u32)>,
+) {
+    let record = TwoBitRecord::encode("s", seq).unwrap();
+
+    assert_eq!(record.n_blocks, expected_n_blocks);
+    assert_eq!(record.mask_blocks, expected_mask_blocks);
+    assert_eq!(record.packed.len(), seq.len().div_ceil(4));
+}
+
+#[rstest]
+#[case(0, 100, b"NNACGTacgtNNTTGCAnnnAC".to_vec())]
+#[case(3, 9, b"CGTacg".to_vec())]
+#[case(9, 19, b"tNNTTGCAnn".to_vec())]
+#[case(20, 100, b"AC".to_vec())]
+
+fn test_twobit_roundtrip(#[case] start: u64, #[case] end: u64, #[case] expected: Vec<u8>) {
+    let records = vec![
+        TwoBitRecord::encode("empty", b"").unwrap(),
+        TwoBitRecord::encode("s1", b"NNACGTacgtNNTTGCAnnnAC").unwrap(),
+    ];
+    let (_dir, path) = write_temp_file("test.2bit", None, |writer| write_twobit(writer, &records));
+
+    let twobit = TwoBitFile::open(&path).unwrap();
+    assert!(TwoBitFile::is_twobit(&path).unwrap());
+    assert_eq!(twobit.names(), &["empty".to_string(), "s1".to_string()]);
+    assert_eq!(twobit.fetch("s1", start, end).unwrap(), expected);
+}
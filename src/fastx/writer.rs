@@ -0,0 +1,213 @@
+use crate::args::{CompressType, GlobalOpts};
+use crate::errors::AppError;
+use crate::fastx::Header;
+use crate::fastx::compression::BlockCompressWriter;
+use rstest::*;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Output options shared by all subcommands that write sequences.
+#[derive(Debug, Clone)]
+pub struct WriterOpts {
+    pub line_width: usize,
+    pub id_only: bool,
+    pub compress: Option<CompressType>,
+}
+
+impl From<&GlobalOpts> for WriterOpts {
+    fn from(global_opts: &GlobalOpts) -> Self {
+        WriterOpts {
+            line_width: global_opts.line_width,
+            id_only: global_opts.id_only,
+            compress: global_opts.compress,
+        }
+    }
+}
+
+impl WriterOpts {
+    /// File name for subcommands that name their own output files (e.g. `split`, `chunk`).
+    pub fn file_name(&self, stem: &str, extension: &str) -> String {
+        match self.compress.as_ref().and_then(CompressType::extension) {
+            Some(compressed) => format!("{stem}.{extension}.{compressed}"),
+            None => format!("{stem}.{extension}"),
+        }
+    }
+}
+
+/// Buffered, optionally compressed, output from `get_writer`. It must be finished after
+/// writing, so that errors at the end of compressed output are not lost.
+pub enum OutputWriter {
+    Plain(BufWriter<Box<dyn Write + Send>>),
+    Compressed(BlockCompressWriter<BufWriter<Box<dyn Write + Send>>>),
+}
+
+impl OutputWriter {
+    /// Writes any buffered data and ends compressed output.
+    pub fn finish(&mut self) -> Result<(), AppError> {
+        match self {
+            OutputWriter::Plain(writer) => writer.flush()?,
+            OutputWriter::Compressed(writer) => writer.finish()?,
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            OutputWriter::Plain(writer) => writer.write(buf),
+            OutputWriter::Compressed(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            OutputWriter::Plain(writer) => writer.flush(),
+            OutputWriter::Compressed(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Creates a buffered writer for a file or stdout.
+///
+/// Output is compressed if `--compress` is set, otherwise compression is
+/// inferred from the file extension.
+pub fn get_writer(outfile: Option<PathBuf>, opts: &WriterOpts) -> Result<OutputWriter, AppError> {
+    let compress_type = opts
+        .compress
+        .or_else(|| outfile.as_deref().and_then(CompressType::from_path))
+        .unwrap_or(CompressType::None);
+
+    let writer: Box<dyn Write + Send> = match outfile {
+        Some(outfile) => Box::new(File::create(outfile)?),
+        None => Box::new(std::io::stdout()),
+    };
+
+    match compress_type {
+        CompressType::None => Ok(OutputWriter::Plain(BufWriter::new(writer))),
+        compress_type => Ok(OutputWriter::Compressed(BlockCompressWriter::new(
+            BufWriter::new(writer),
+            compress_type,
+        ))),
+    }
+}
+
+/// Writes `name` in a new temporary directory through `get_writer`, for round-trip tests.
+/// The directory is removed when the returned `TempDir` is dropped, also if the test fails.
+#[cfg(test)]
+pub fn write_temp_file<F>(
+    name: &str,
+    compress: Option<CompressType>,
+    write: F,
+) -> (tempfile::TempDir, PathBuf)
+where
+    F: FnOnce(&mut OutputWriter) -> Result<(), AppError>,
+{
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+
+    let opts = WriterOpts {
+        line_width: 0,
+        id_only: false,
+        compress,
+    };
+
+    let mut writer = get_writer(Some(path.clone()), &opts).unwrap();
+    write(&mut writer).unwrap();
+    writer.finish().unwrap();
+
+    (dir, path)
+}
+
+/// Writes `seq` split into lines of at most `line_width` bases. A width of 0 means unwrapped.
+#[inline]
+fn write_wrapped<W: Write + ?Sized>(
+    writer: &mut W,
+    seq: &[u8],
+    line_width: usize,
+) -> Result<(), std::io::Error> {
+    if line_width == 0 || seq.len() <= line_width {
+        writer.write_all(seq)?;
+        writer.write_all(b"\n")?;
+        return Ok(());
+    }
+
+    for line in seq.chunks(line_width) {
+        writer.write_all(line)?;
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+/// Shared FASTA/FASTQ writer, so that headers and line wrapping are handled identically everywhere.
+pub struct FastxWriter {
+    writer: OutputWriter,
+    line_width: usize,
+    id_only: bool,
+}
+
+impl FastxWriter {
+    pub fn new(outfile: Option<PathBuf>, opts: &WriterOpts) -> Result<Self, AppError> {
+        Ok(FastxWriter {
+            writer: get_writer(outfile, opts)?,
+            line_width: opts.line_width,
+            id_only: opts.id_only,
+        })
+    }
+
+    /// Writes a FASTQ record if `qual` is provided, otherwise a FASTA record.
+    pub fn write(
+        &mut self,
+        header: &Header,
+        seq: &[u8],
+        qual: Option<&[u8]>,
+    ) -> Result<(), AppError> {
+        // Header.
+        match qual {
+            Some(_) => self.writer.write_all(b"@")?,
+            None => self.writer.write_all(b">")?,
+        }
+        self.writer.write_all(header.id)?;
+
+        if let (Some(desc), false) = (header.desc, self.id_only) {
+            self.writer.write_all(b" ")?;
+            self.writer.write_all(desc)?;
+        }
+        self.writer.write_all(b"\n")?;
+
+        // Sequence. FASTQ is never wrapped.
+        match qual {
+            Some(qual) => {
+                self.writer.write_all(seq)?;
+                self.writer.write_all(b"\n+\n")?;
+                self.writer.write_all(qual)?;
+                self.writer.write_all(b"\n")?;
+            }
+            None => write_wrapped(&mut self.writer, seq, self.line_width)?,
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<(), AppError> {
+        self.writer.finish()
+    }
+}
+
+#[rstest]
+#[case(b"", 0, b"\n")]
+#[case(b"ACGT", 0, b"ACGT\n")]
+#[case(b"ACGT", 4, b"ACGT\n")]
+#[case(b"ACGT", 10, b"ACGT\n")]
+#[case(b"ACGTA", 2, b"AC\nGT\nA\n")]
+#[case(b"ACGTAC", 3, b"ACG\nTAC\n")]
+
+fn test_write_wrapped(#[case] seq: &[u8], #[case] line_width: usize, #[case] expected: &[u8]) {
+    let mut buf: Vec<u8> = Vec::new();
+    write_wrapped(&mut buf, seq, line_width).unwrap();
+
+    assert_eq!(&buf[..], expected);
+}
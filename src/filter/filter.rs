@@ -1,8 +1,30 @@
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
-use bio_utils_rs::nucleotide::{nucleotide_counts, nucleotide_probabilities, shannon_entropy};
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader};
+use bio_utils_rs::nucleotide::{
+    PHRED_TO_ERROR, nucleotide_counts, nucleotide_probabilities, shannon_entropy,
+};
+use rstest::*;
 use std::path::PathBuf;
 
+/// Mean Phred quality (computed from the mean error probability) and expected number of errors.
+#[inline]
+fn quality_metrics(qual: &[u8]) -> (f64, f64) {
+    if qual.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let max_index = PHRED_TO_ERROR.len() - 1;
+
+    let expected_errors: f64 = qual
+        .iter()
+        .map(|q| PHRED_TO_ERROR[(*q as usize).min(max_index)])
+        .sum();
+
+    let mean_error = expected_errors / qual.len() as f64;
+
+    (-10.0 * mean_error.log10(), expected_errors)
+}
+
 #[allow(unused)]
 #[allow(clippy::too_many_arguments)]
 pub fn fasta_filter(
@@ -17,10 +39,13 @@ pub fn fasta_filter(
     max_softmask: f32,
     min_entropy: f32,
     max_entropy: f32,
+    min_qual: f64,
+    max_ee: Option<f64>,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
-    let mut reader = needletail_reader(fasta)?;
-    let mut writer = get_bufwriter(outfile)?;
+    let mut reader = fastx_reader(fasta)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
 
     while let Some(record) = reader.next() {
         let record = match record {
@@ -71,10 +96,40 @@ pub fn fasta_filter(
             continue;
         }
 
-        record.write(&mut writer, None)?;
+        // Quality, FASTA records have no qualities and always pass.
+        if let Some(qual) = record.qual() {
+            let (mean_qual, expected_errors) = quality_metrics(qual);
+
+            if mean_qual < min_qual {
+                continue;
+            }
+
+            if max_ee.is_some_and(|max_ee| expected_errors > max_ee) {
+                continue;
+            }
+        }
+
+        writer.write(&Header::parse(record.id()), &record_seq, record.qual())?;
     }
 
-    writer.flush()?;
+    writer.finish()?;
 
     Ok(())
 }
+
+#[rstest]
+#[case(b"", 0.0, 0.0)]
+#[case(b"+++", 10.0, 0.3)]
+#[case(b"55", 20.0, 0.02)]
+#[case(b"+5", 12.596_373_105_057_56, 0.11)]
+
+fn test_quality_metrics(
+    #[case] qual: &[u8],
+    #[case] expected_mean_qual: f64,
+    #[case] expected_errors: f64,
+) {
+    let (mean_qual, errors) = quality_metrics(qual);
+
+    assert!((mean_qual - expected_mean_qual).abs() < 1e-9);
+    assert!((errors - expected_errors).abs() < 1e-9);
+}
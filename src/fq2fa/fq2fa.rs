@@ -0,0 +1,75 @@
+use crate::args::MaskMode;
+use crate::errors::AppError;
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader};
+use bio_utils_rs::nucleotide::PHRED_OFFSET;
+use rstest::*;
+use std::path::PathBuf;
+
+/// Masks bases with a Phred quality below `min_qual`, either to `N` (hard) or lowercase (soft).
+fn mask_low_quality(seq: &[u8], qual: &[u8], min_qual: u8, mask_mode: &MaskMode) -> Vec<u8> {
+    seq.iter()
+        .zip(qual)
+        .map(|(nt, q)| {
+            if (q.saturating_sub(PHRED_OFFSET as u8)) >= min_qual {
+                return *nt;
+            }
+
+            match mask_mode {
+                MaskMode::Hard => b'N',
+                MaskMode::Soft => nt.to_ascii_lowercase(),
+            }
+        })
+        .collect()
+}
+
+pub fn fasta_fq2fa(
+    fastq: Option<PathBuf>,
+    mask_qual: Option<u8>,
+    mask_mode: MaskMode,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let mut reader = fastx_reader(fastq)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        let header = Header::parse(record.id());
+
+        match (record.qual(), mask_qual) {
+            (Some(qual), Some(mask_qual)) => {
+                let masked = mask_low_quality(&record.seq(), qual, mask_qual, &mask_mode);
+                writer.write(&header, &masked, None)?;
+            }
+            _ => writer.write(&header, &record.seq(), None)?,
+        }
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(b"ACGT", b"IIII", 20, MaskMode::Hard, b"ACGT")]
+#[case(b"ACGT", b"I+I+", 20, MaskMode::Hard, b"ANGN")]
+#[case(b"ACGT", b"I+I+", 20, MaskMode::Soft, b"AcGt")]
+#[case(b"ACGT", b"5555", 20, MaskMode::Hard, b"ACGT")]
+#[case(b"ACGT", b"4444", 20, MaskMode::Hard, b"NNNN")]
+
+fn test_mask_low_quality(
+    #[case] seq: &[u8],
+    #[case] qual: &[u8],
+    #[case] min_qual: u8,
+    #[case] mask_mode: MaskMode,
+    #[case] expected: &[u8],
+) {
+    assert_eq!(
+        &mask_low_quality(seq, qual, min_qual, &mask_mode)[..],
+        expected
+    );
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod fq2fa;
+pub use fq2fa::fasta_fq2fa;
@@ -0,0 +1,103 @@
+use crate::errors::AppError;
+use crate::fastx::genbank::{Feature, FlatFileReader, Location};
+use crate::fastx::reader::open_input;
+use crate::fastx::{FastxWriter, Header, WriterOpts};
+use log::warn;
+use rstest::*;
+use std::path::PathBuf;
+
+/// `id|kind|start-end|strand` followed by the requested qualifiers as `key=value`.
+fn feature_header(
+    record_id: &str,
+    feature: &Feature,
+    location: &Location,
+    qualifiers: &[String],
+) -> String {
+    let (start, end) = location.span();
+    let strand = match location.is_complement() {
+        true => '-',
+        false => '+',
+    };
+
+    let mut header = format!("{record_id}|{}|{start}-{end}|{strand}", feature.kind);
+
+    for key in qualifiers {
+        if let Some(value) = feature.qualifier(key) {
+            header.push_str(&format!(" {key}={value}"));
+        }
+    }
+
+    header
+}
+
+pub fn fasta_gb2fa(
+    genbank: Option<PathBuf>,
+    features: Vec<String>,
+    qualifiers: Vec<String>,
+    features_only: bool,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let reader = FlatFileReader::new(open_input(genbank.as_deref())?);
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    for record in reader {
+        let record = record?;
+
+        if !features_only {
+            let header = record.header();
+            writer.write(&Header::parse(header.as_bytes()), &record.seq, None)?;
+        }
+
+        for feature in record
+            .features
+            .iter()
+            .filter(|f| features.contains(&f.kind))
+        {
+            let location = match Location::parse(&feature.location) {
+                Ok(location) => location,
+                Err(e) => {
+                    warn!("Skipping {} in {}: {e}", feature.kind, record.id);
+                    continue;
+                }
+            };
+
+            let header = feature_header(&record.id, feature, &location, &qualifiers);
+            let seq = location.extract(&record.seq);
+
+            writer.write(&Header::parse(header.as_bytes()), &seq, None)?;
+        }
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case("1..9", vec![("locus_tag", "T_0001"), ("product", "a protein")], "rec|CDS|0-9|+ locus_tag=T_0001 product=a protein")]
+#[case("complement(join(1..3,7..9))", vec![("locus_tag", "T_0001")], "rec|CDS|0-9|- locus_tag=T_0001")]
+#[case("join(complement(7..9),complement(1..3))", vec![], "rec|CDS|0-9|-")]
+
+fn test_feature_header(
+    #[case] location: &str,
+    #[case] feature_qualifiers: Vec<(&str, &str)>,
+    #[case] expected: &str,
+) {
+    let feature = Feature {
+        kind: "CDS".into(),
+        location: location.into(),
+        qualifiers: feature_qualifiers
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+    };
+
+    let qualifiers = vec!["locus_tag".to_string(), "product".to_string()];
+    let location = Location::parse(location).unwrap();
+
+    assert_eq!(
+        feature_header("rec", &feature, &location, &qualifiers),
+        expected
+    );
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod gb2fa;
+pub use gb2fa::fasta_gb2fa;
@@ -1,15 +1,16 @@
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader};
 use regex::Regex;
 use std::path::PathBuf;
 
 pub fn fasta_grep(
-    fastq: Option<PathBuf>,
+    fasta: Option<PathBuf>,
     pattern: String,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
-    let mut reader = needletail_reader(fastq)?;
-    let mut writer = get_bufwriter(outfile)?;
+    let mut reader = fastx_reader(fasta)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
 
     let pattern = Regex::new(pattern.as_str())?;
 
@@ -20,10 +21,12 @@ pub fn fasta_grep(
         };
 
         match pattern.captures(std::str::from_utf8(record.id())?) {
-            Some(_) => record.write(&mut writer, None)?,
+            Some(_) => writer.write(&Header::parse(record.id()), &record.seq(), record.qual())?,
             None => continue,
         }
     }
 
+    writer.finish()?;
+
     Ok(())
 }
@@ -1,14 +1,34 @@
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
+use crate::fastx::{FastxWriter, Header, IndexedFasta, WriterOpts, fastx_reader};
+use needletail::parse_fastx_reader;
 use std::path::PathBuf;
 
 pub fn fasta_head(
     fasta: Option<PathBuf>,
     num_seqs: usize,
+    skip: usize,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
-    let mut reader = needletail_reader(fasta)?;
-    let mut writer = get_bufwriter(outfile)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    // If the fasta is indexed, we can jump directly to the first record.
+    let indexed = match &fasta {
+        Some(path) if skip > 0 && IndexedFasta::has_index(path)? => Some(IndexedFasta::open(path)?),
+        _ => None,
+    };
+
+    let (mut reader, mut to_skip) = match indexed {
+        Some(index) if skip >= index.records().len() => {
+            writer.finish()?;
+            return Ok(());
+        }
+        Some(index) => (
+            parse_fastx_reader(index.reader_at(index.record_start(skip))?)?,
+            0,
+        ),
+        None => (fastx_reader(fasta)?, skip),
+    };
 
     let mut n: usize = 0;
     while let Some(record) = reader.next() {
@@ -17,17 +37,22 @@ pub fn fasta_head(
             Err(_) => continue,
         };
 
+        if to_skip > 0 {
+            to_skip -= 1;
+            continue;
+        }
+
         // We only count valid records.
         n += 1;
 
-        record.write(&mut writer, None)?;
+        writer.write(&Header::parse(record.id()), &record.seq(), record.qual())?;
 
         if n >= num_seqs {
             break;
         }
     }
 
-    writer.flush()?;
+    writer.finish()?;
 
     Ok(())
 }
@@ -1,7 +1,11 @@
+use crate::args::HomopolymerFormat;
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
-use needletail::parser::SequenceRecord;
+use crate::fastx::{
+    OutputWriter, OwnedRecord, WriterOpts, fastx_reader, for_each_batch, get_writer,
+};
+use rayon::prelude::*;
 use rstest::*;
+use std::collections::BTreeMap;
 use std::io::Write;
 use std::path::PathBuf;
 
@@ -32,20 +36,27 @@ fn valid_homopolymer(i: usize, j: usize, nt: &u8, min_hp_len: usize, strict: boo
         (true, None) => false,
     }
 }
-#[inline]
-pub fn find_homopolymers_in_record(
-    record: &SequenceRecord,
-    min_hp_len: usize,
-    strict: bool,
-    writer: &mut Box<dyn Write + Send>,
-) -> Result<(), AppError> {
-    // Extract sequence information.
-    let seq = record.seq();
+/// Homopolymer run in BED coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Homopolymer {
+    pub start: usize,
+    pub end: usize,
+    pub nt: u8,
+}
+
+impl Homopolymer {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+pub fn find_homopolymers(seq: &[u8], min_hp_len: usize, strict: bool) -> Vec<Homopolymer> {
     let seq_len = seq.len();
+    let mut homopolymers: Vec<Homopolymer> = Vec::new();
 
     // Skip sequence if shorter than hp len.
-    if seq_len < min_hp_len {
-        return Ok(());
+    if seq_len < min_hp_len || seq_len == 0 {
+        return homopolymers;
     }
 
     let mut i = 0;
@@ -58,31 +69,219 @@ pub fn find_homopolymers_in_record(
 
         // We have a homopolymer of required length.
         if valid_homopolymer(i, j, &seq[i], min_hp_len, strict) {
-            // Id.
-            writer.write_all(record.id())?;
-            writer.write_all(b"\t")?;
-            // Start of hp.
-            writer.write_all(i.to_string().as_bytes())?;
-            writer.write_all(b"\t")?;
-
-            // End of hp.
-            writer.write_all(j.to_string().as_bytes())?;
-            writer.write_all(b"\t")?;
-
-            // Hp length.
-            writer.write_all((j - i).to_string().as_bytes())?;
-            writer.write_all(b"\t")?;
-
-            // Hp nucleotide.
-            writer.write_all(&[seq[i]])?;
-            writer.write_all(b"\n")?;
+            homopolymers.push(Homopolymer {
+                start: i,
+                end: j,
+                nt: seq[i],
+            });
         }
 
         i = j;
         j += 1;
     }
 
-    writer.flush()?;
+    homopolymers
+}
+
+/// Homopolymer length histogram per nucleotide (case-insensitive) and longest homopolymer.
+#[derive(Debug, Default, PartialEq)]
+pub struct HomopolymerSummary {
+    pub seq_len: usize,
+    pub count: usize,
+    pub histogram: BTreeMap<(u8, usize), usize>,
+    pub longest: Option<Homopolymer>,
+}
+
+impl HomopolymerSummary {
+    pub fn new(seq_len: usize, homopolymers: &[Homopolymer]) -> Self {
+        let mut summary = HomopolymerSummary {
+            seq_len,
+            ..Default::default()
+        };
+
+        for homopolymer in homopolymers {
+            summary.count += 1;
+            *summary
+                .histogram
+                .entry((homopolymer.nt.to_ascii_uppercase(), homopolymer.len()))
+                .or_insert(0) += 1;
+
+            if summary
+                .longest
+                .is_none_or(|longest| homopolymer.len() > longest.len())
+            {
+                summary.longest = Some(*homopolymer);
+            }
+        }
+
+        summary
+    }
+
+    pub fn merge(&mut self, other: &HomopolymerSummary) {
+        self.seq_len += other.seq_len;
+        self.count += other.count;
+
+        for (key, count) in &other.histogram {
+            *self.histogram.entry(*key).or_insert(0) += count;
+        }
+
+        if let Some(longest) = other.longest
+            && self
+                .longest
+                .is_none_or(|current| longest.len() > current.len())
+        {
+            self.longest = Some(longest);
+        }
+    }
+
+    /// Homopolymers per kb of sequence.
+    pub fn density(&self) -> f64 {
+        match self.seq_len {
+            0 => 0.0,
+            seq_len => 1000.0 * self.count as f64 / seq_len as f64,
+        }
+    }
+}
+
+fn write_homopolymer(
+    writer: &mut impl Write,
+    id: &[u8],
+    homopolymer: &Homopolymer,
+    output_format: &HomopolymerFormat,
+) -> Result<(), AppError> {
+    writer.write_all(id)?;
+
+    match output_format {
+        HomopolymerFormat::Bed => writeln!(
+            writer,
+            "\t{}\t{}\t{}\t{}",
+            homopolymer.start,
+            homopolymer.end,
+            homopolymer.nt as char,
+            homopolymer.len()
+        )?,
+        _ => writeln!(
+            writer,
+            "\t{}\t{}\t{}\t{}",
+            homopolymer.start,
+            homopolymer.end,
+            homopolymer.len(),
+            homopolymer.nt as char
+        )?,
+    }
+
+    Ok(())
+}
+
+/// Per record summary lines, and length histograms per record and for all records (`*`).
+fn write_summary(
+    writer: &mut OutputWriter,
+    summaries: &[(String, HomopolymerSummary)],
+) -> Result<(), AppError> {
+    let mut total = HomopolymerSummary::default();
+
+    writeln!(writer, "# records")?;
+    writeln!(
+        writer,
+        "contig\tlength\thomopolymers\tper_kb\tlongest_len\tlongest_nt\tlongest_start"
+    )?;
+
+    for (id, summary) in summaries {
+        let (longest_len, longest_nt, longest_start) = match summary.longest {
+            Some(longest) => (
+                longest.len().to_string(),
+                (longest.nt as char).to_string(),
+                longest.start.to_string(),
+            ),
+            None => ("0".to_string(), ".".to_string(), ".".to_string()),
+        };
+
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{:.3}\t{}\t{}\t{}",
+            id,
+            summary.seq_len,
+            summary.count,
+            summary.density(),
+            longest_len,
+            longest_nt,
+            longest_start
+        )?;
+
+        total.merge(summary);
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "# histogram")?;
+    writeln!(writer, "contig\tnt\tlen\tcount")?;
+
+    for (id, summary) in summaries
+        .iter()
+        .map(|(id, s)| (id.as_str(), s))
+        .chain([("*", &total)])
+    {
+        for ((nt, len), count) in &summary.histogram {
+            writeln!(writer, "{}\t{}\t{}\t{}", id, *nt as char, len, count)?;
+        }
+    }
+
+    Ok(())
+}
+
+enum RecordOutput {
+    Lines(Vec<u8>),
+    Summary(String, HomopolymerSummary),
+}
+
+fn process_record(
+    record: &OwnedRecord,
+    min_hp_len: usize,
+    strict: bool,
+    output_format: &HomopolymerFormat,
+) -> Result<RecordOutput, AppError> {
+    let homopolymers = find_homopolymers(&record.seq, min_hp_len, strict);
+
+    // The TSV keeps the full header, BED and summary only the id.
+    let id = match output_format {
+        HomopolymerFormat::Tsv => &record.header,
+        _ => record.header().id,
+    };
+
+    if let HomopolymerFormat::Summary = output_format {
+        return Ok(RecordOutput::Summary(
+            String::from_utf8_lossy(id).into_owned(),
+            HomopolymerSummary::new(record.seq.len(), &homopolymers),
+        ));
+    }
+
+    let mut lines: Vec<u8> = Vec::new();
+    for homopolymer in &homopolymers {
+        write_homopolymer(&mut lines, id, homopolymer, output_format)?;
+    }
+
+    Ok(RecordOutput::Lines(lines))
+}
+
+/// Processes a batch of records in parallel, writing the output in input order.
+fn process_batch(
+    batch: &[OwnedRecord],
+    min_hp_len: usize,
+    strict: bool,
+    output_format: &HomopolymerFormat,
+    writer: &mut OutputWriter,
+    summaries: &mut Vec<(String, HomopolymerSummary)>,
+) -> Result<(), AppError> {
+    let outputs: Vec<RecordOutput> = batch
+        .par_iter()
+        .map(|record| process_record(record, min_hp_len, strict, output_format))
+        .collect::<Result<_, _>>()?;
+
+    for output in outputs {
+        match output {
+            RecordOutput::Lines(lines) => writer.write_all(&lines)?,
+            RecordOutput::Summary(id, summary) => summaries.push((id, summary)),
+        }
+    }
 
     Ok(())
 }
@@ -91,30 +290,37 @@ pub fn fasta_homopolymers(
     fasta: Option<PathBuf>,
     min_hp_len: usize,
     strict: bool,
+    output_format: &HomopolymerFormat,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
-    let mut reader = needletail_reader(fasta)?;
+    let reader = fastx_reader(fasta)?;
 
     // Output file writer.
-    let mut writer = get_bufwriter(outfile)?;
+    let mut writer = get_writer(outfile, writer_opts)?;
 
-    // Write tsv header.
-    let s = format!(
-        "{}\t{}\t{}\t{}\t{}\n",
-        "contig", "start", "end", "len", "nt"
-    );
-    writer.write_all(s.as_bytes()).unwrap();
+    if let HomopolymerFormat::Tsv = output_format {
+        writeln!(writer, "contig\tstart\tend\tlen\tnt")?;
+    }
 
-    while let Some(record) = reader.next() {
-        let record = match record {
-            Ok(record) => record,
-            Err(_) => continue,
-        };
+    let mut summaries: Vec<(String, HomopolymerSummary)> = Vec::new();
+
+    for_each_batch(reader, |batch| {
+        process_batch(
+            batch,
+            min_hp_len,
+            strict,
+            output_format,
+            &mut writer,
+            &mut summaries,
+        )
+    })?;
 
-        find_homopolymers_in_record(&record, min_hp_len, strict, &mut writer)?
+    if let HomopolymerFormat::Summary = output_format {
+        write_summary(&mut writer, &summaries)?;
     }
 
-    writer.flush()?;
+    writer.finish()?;
 
     Ok(())
 }
@@ -137,3 +343,47 @@ fn test_valid_homopolymer(
         expected_valid
     );
 }
+
+#[rstest]
+#[case(b"", 2, false, vec![])]
+#[case(b"AAACGGNNNT", 2, false, vec![(0, 3, b'A'), (4, 6, b'G'), (6, 9, b'N')])]
+#[case(b"AAACGGNNNT", 2, true, vec![(0, 3, b'A'), (4, 6, b'G')])]
+#[case(b"ACGTTT", 3, true, vec![(3, 6, b'T')])]
+
+fn test_find_homopolymers(
+    #[case] seq: &[u8],
+    #[case] min_hp_len: usize,
+    #[case] strict: bool,
+    #[case] expected: Vec<(usize, usize, u8)>,
+) {
+    let homopolymers: Vec<(usize, usize, u8)> = find_homopolymers(seq, min_hp_len, strict)
+        .iter()
+        .map(|hp| (hp.start, hp.end, hp.nt))
+        .collect();
+
+    assert_eq!(homopolymers, expected);
+}
+
+#[rstest]
+fn test_homopolymer_summary() {
+    let mut summary = HomopolymerSummary::new(2000, &find_homopolymers(b"AAACGGaaaTTTTT", 3, true));
+    summary.merge(&HomopolymerSummary::new(
+        0,
+        &find_homopolymers(b"AAA", 3, true),
+    ));
+
+    assert_eq!(summary.count, 4);
+    assert_eq!(summary.density(), 2.0);
+    assert_eq!(
+        summary.histogram,
+        BTreeMap::from([((b'A', 3), 3), ((b'T', 5), 1)])
+    );
+    assert_eq!(
+        summary.longest,
+        Some(Homopolymer {
+            start: 9,
+            end: 14,
+            nt: b'T'
+        })
+    );
+}
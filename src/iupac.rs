@@ -0,0 +1,223 @@
+use memchr::memmem;
+use rstest::*;
+
+/// Ambiguous IUPAC nucleotide codes and the bases they represent.
+pub const AMBIGUOUS_CODES: [(u8, &[u8]); 11] = [
+    (b'R', b"AG"),
+    (b'Y', b"CT"),
+    (b'S', b"GC"),
+    (b'W', b"AT"),
+    (b'K', b"GT"),
+    (b'M', b"AC"),
+    (b'B', b"CGT"),
+    (b'D', b"AGT"),
+    (b'H', b"ACT"),
+    (b'V', b"ACG"),
+    (b'N', b"ACGT"),
+];
+
+/// Concrete bases represented by a nucleotide code (case-insensitive, U is read as T),
+/// or `None` if it is not an IUPAC nucleotide code.
+pub fn bases(code: u8) -> Option<&'static [u8]> {
+    match code.to_ascii_uppercase() {
+        b'A' => Some(b"A"),
+        b'C' => Some(b"C"),
+        b'G' => Some(b"G"),
+        b'T' | b'U' => Some(b"T"),
+        code => AMBIGUOUS_CODES
+            .iter()
+            .find(|(ambiguous, _)| *ambiguous == code)
+            .map(|(_, bases)| *bases),
+    }
+}
+
+pub fn is_nucleotide(code: u8) -> bool {
+    bases(code).is_some()
+}
+
+pub fn is_degenerate(seq: &[u8]) -> bool {
+    seq.iter()
+        .any(|code| bases(*code).is_some_and(|bases| bases.len() > 1))
+}
+
+/// IUPAC aware complement that preserves softmasking (case).
+#[inline]
+pub fn complement_base(nt: u8) -> u8 {
+    match nt {
+        // Canonical.
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' | b'U' => b'A',
+        b'a' => b't',
+        b'c' => b'g',
+        b'g' => b'c',
+        b't' | b'u' => b'a',
+        // Ambiguous.
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        b'r' => b'y',
+        b'y' => b'r',
+        b'k' => b'm',
+        b'm' => b'k',
+        b'b' => b'v',
+        b'v' => b'b',
+        b'd' => b'h',
+        b'h' => b'd',
+        // S, W, N and anything else (e.g. gaps) are their own complement.
+        nt => nt,
+    }
+}
+
+pub fn reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter().rev().map(|nt| complement_base(*nt)).collect()
+}
+
+/// Bit mask of the bases represented by a pattern code (A = 1, C = 2, G = 4, T = 8).
+fn pattern_mask(code: u8) -> u8 {
+    bases(code).map_or(0, |bases| {
+        bases.iter().fold(0, |mask, base| {
+            mask | match base {
+                b'A' => 1,
+                b'C' => 2,
+                b'G' => 4,
+                _ => 8,
+            }
+        })
+    })
+}
+
+/// Bit mask of a text base. Like exact matching of concrete patterns, only uppercase
+/// A, C, G and T match, so ambiguous bases in the text never match.
+fn text_mask(base: u8) -> u8 {
+    match base {
+        b'A' => 1,
+        b'C' => 2,
+        b'G' => 4,
+        b'T' => 8,
+        _ => 0,
+    }
+}
+
+/// Start positions of all exact matches of a possibly degenerate pattern in `seq`.
+/// Concrete patterns are searched with `memmem`, degenerate patterns of up to 64
+/// bases with the bit-parallel Shift-And algorithm.
+pub fn find_all(seq: &[u8], pattern: &[u8]) -> Vec<usize> {
+    if pattern.is_empty() || pattern.len() > seq.len() {
+        return Vec::new();
+    }
+
+    if !is_degenerate(pattern) {
+        return memmem::find_iter(seq, pattern).collect();
+    }
+
+    let masks: Vec<u8> = pattern.iter().map(|code| pattern_mask(*code)).collect();
+
+    if pattern.len() > 64 {
+        return seq
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(_, window)| {
+                window
+                    .iter()
+                    .zip(&masks)
+                    .all(|(base, mask)| text_mask(*base) & mask != 0)
+            })
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    // For each text base, the pattern positions it matches.
+    let mut position_masks = [0u64; 16];
+    for (i, mask) in masks.iter().enumerate() {
+        for (text, position_mask) in position_masks.iter_mut().enumerate() {
+            if text as u8 & mask != 0 {
+                *position_mask |= 1 << i;
+            }
+        }
+    }
+
+    let last = 1u64 << (pattern.len() - 1);
+    let mut state = 0u64;
+    let mut starts: Vec<usize> = Vec::new();
+
+    for (i, base) in seq.iter().enumerate() {
+        state = ((state << 1) | 1) & position_masks[text_mask(*base) as usize];
+        if state & last != 0 {
+            starts.push(i + 1 - pattern.len());
+        }
+    }
+
+    starts
+}
+
+/// Number of concrete sequences represented by `seq`, or `None` on overflow.
+pub fn expansion_count(seq: &[u8]) -> Option<usize> {
+    seq.iter().try_fold(1usize, |count, code| {
+        count.checked_mul(bases(*code).map_or(1, |bases| bases.len()))
+    })
+}
+
+/// All concrete sequences represented by `seq`, in lexicographic ACGT order. The case of
+/// each position is kept, and symbols that are not nucleotide codes are copied as is.
+pub fn expand(seq: &[u8]) -> Vec<Vec<u8>> {
+    let mut expanded: Vec<Vec<u8>> = vec![Vec::with_capacity(seq.len())];
+
+    for &code in seq {
+        let options: Vec<u8> = match bases(code) {
+            Some(bases) if bases.len() > 1 => match code.is_ascii_lowercase() {
+                true => bases.to_ascii_lowercase(),
+                false => bases.to_vec(),
+            },
+            _ => vec![code],
+        };
+
+        expanded = expanded
+            .into_iter()
+            .flat_map(|prefix| {
+                options.iter().map(move |base| {
+                    let mut next = prefix.clone();
+                    next.push(*base);
+                    next
+                })
+            })
+            .collect();
+    }
+
+    expanded
+}
+
+#[rstest]
+#[case(b"ACGT", b"TTACGTACGT", vec![2, 6])]
+#[case(b"ACRT", b"TTACGTACAT", vec![2, 6])]
+#[case(b"NNN", b"ACGT", vec![0, 1])]
+// Ambiguous and lowercase bases in the text do not match.
+#[case(b"ACN", b"ACNacgACG", vec![6])]
+#[case(b"ACGTN", b"ACG", vec![])]
+
+fn test_find_all(#[case] pattern: &[u8], #[case] seq: &[u8], #[case] expected: Vec<usize>) {
+    assert_eq!(find_all(seq, pattern), expected);
+}
+
+#[rstest]
+#[case(b"ACGT", Some(1))]
+#[case(b"ARYN", Some(16))]
+#[case(&[b'N'; 40], None)]
+
+fn test_expansion_count(#[case] seq: &[u8], #[case] expected: Option<usize>) {
+    assert_eq!(expansion_count(seq), expected);
+}
+
+#[rstest]
+#[case(b"ACGT", vec![b"ACGT".to_vec()])]
+#[case(b"AR-y", vec![b"AA-c".to_vec(), b"AA-t".to_vec(), b"AG-c".to_vec(), b"AG-t".to_vec()])]
+
+fn test_expand(#[case] seq: &[u8], #[case] expected: Vec<Vec<u8>>) {
+    assert_eq!(expand(seq), expected);
+}
@@ -0,0 +1,324 @@
+use crate::errors::AppError;
+use crate::fastx::{
+    OutputWriter, OwnedRecord, WriterOpts, fastx_reader, for_each_batch, get_writer,
+};
+use needletail::bitkmer::{bitmer_to_bytes, canonical};
+use rayon::prelude::*;
+use rstest::*;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasherDefault, Hasher};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// k-mers are packed with two bits per base, so at most 32 bases fit in a u64.
+pub const MAX_KMER_SIZE: usize = 32;
+
+/// 64-bit hash of a packed k-mer (splitmix64).
+#[inline]
+pub fn hash_kmer(kmer: u64) -> u64 {
+    let mut x = kmer.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Hasher for packed k-mers. The default SipHash is needlessly slow for u64 keys.
+#[derive(Default)]
+pub struct KmerHasher(u64);
+
+impl Hasher for KmerHasher {
+    fn finish(&self) -> u64 {
+        hash_kmer(self.0)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 << 8) | *byte as u64;
+        }
+    }
+
+    fn write_u64(&mut self, kmer: u64) {
+        self.0 = kmer;
+    }
+}
+
+pub type KmerCounts = HashMap<u64, u64, BuildHasherDefault<KmerHasher>>;
+
+#[inline]
+fn nt2bit(nt: u8) -> Option<u64> {
+    match nt {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+/// Packed k-mers of a sequence (A = 0, C = 1, G = 2, T = 3, first base in the highest bits),
+/// case-insensitive. k-mers containing non-ACGT bases are skipped. With `canonical`, the
+/// smallest of each k-mer and its reverse complement is returned.
+pub struct PackedKmers<'a> {
+    seq: &'a [u8],
+    k: usize,
+    canonical: bool,
+    mask: u64,
+    pos: usize,
+    /// Number of consecutive ACGT bases ending at the previous position.
+    valid: usize,
+    kmer: u64,
+}
+
+impl<'a> PackedKmers<'a> {
+    pub fn new(seq: &'a [u8], k: usize, canonical: bool) -> Self {
+        assert!((1..=MAX_KMER_SIZE).contains(&k));
+
+        PackedKmers {
+            seq,
+            k,
+            canonical,
+            mask: u64::MAX >> (64 - 2 * k),
+            pos: 0,
+            valid: 0,
+            kmer: 0,
+        }
+    }
+}
+
+impl Iterator for PackedKmers<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        while self.pos < self.seq.len() {
+            let nt = self.seq[self.pos];
+            self.pos += 1;
+
+            match nt2bit(nt) {
+                Some(bits) => {
+                    self.kmer = ((self.kmer << 2) | bits) & self.mask;
+                    self.valid += 1;
+                }
+                None => {
+                    self.valid = 0;
+                    continue;
+                }
+            }
+
+            if self.valid >= self.k {
+                return Some(match self.canonical {
+                    true => canonical((self.kmer, self.k as u8)).0.0,
+                    false => self.kmer,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+pub fn decode_kmer(kmer: u64, k: usize) -> String {
+    String::from_utf8_lossy(&bitmer_to_bytes((kmer, k as u8))).into_owned()
+}
+
+pub fn add_kmers(counts: &mut KmerCounts, seq: &[u8], k: usize, canonical: bool) {
+    for kmer in PackedKmers::new(seq, k, canonical) {
+        *counts.entry(kmer).or_insert(0) += 1;
+    }
+}
+
+/// Merges two counts, inserting the smaller into the larger.
+fn merge_counts(a: KmerCounts, b: KmerCounts) -> KmerCounts {
+    let (mut counts, other) = match a.len() >= b.len() {
+        true => (a, b),
+        false => (b, a),
+    };
+
+    for (kmer, count) in other {
+        *counts.entry(kmer).or_insert(0) += count;
+    }
+
+    counts
+}
+
+/// Number of distinct k-mers per count (k-mer spectrum).
+fn spectrum(counts: &KmerCounts) -> BTreeMap<u64, usize> {
+    let mut spectrum: BTreeMap<u64, usize> = BTreeMap::new();
+
+    for count in counts.values() {
+        *spectrum.entry(*count).or_insert(0) += 1;
+    }
+
+    spectrum
+}
+
+/// The `n` most frequent k-mers, ties broken by k-mer.
+fn top_kmers(counts: &KmerCounts, n: usize) -> Vec<(u64, u64)> {
+    let mut kmers: Vec<(u64, u64)> = counts.iter().map(|(kmer, count)| (*kmer, *count)).collect();
+
+    kmers.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    kmers.truncate(n);
+
+    kmers
+}
+
+/// Counts a batch of records in parallel, adding them to the total counts and writing
+/// per-record profiles in input order.
+fn process_batch(
+    batch: &[OwnedRecord],
+    k: usize,
+    canonical: bool,
+    counts: &mut KmerCounts,
+    profile_writer: &mut Option<OutputWriter>,
+) -> Result<(), AppError> {
+    let batch_counts = match profile_writer {
+        // Without profiles, records are counted straight into per-thread counts.
+        None => batch
+            .par_iter()
+            .fold(KmerCounts::default, |mut counts, record| {
+                add_kmers(&mut counts, &record.seq, k, canonical);
+                counts
+            })
+            .reduce(KmerCounts::default, merge_counts),
+        Some(profile_writer) => {
+            let record_counts: Vec<KmerCounts> = batch
+                .par_iter()
+                .map(|record| {
+                    let mut counts = KmerCounts::default();
+                    add_kmers(&mut counts, &record.seq, k, canonical);
+                    counts
+                })
+                .collect();
+
+            for (record, record_counts) in batch.iter().zip(&record_counts) {
+                let mut kmers: Vec<(&u64, &u64)> = record_counts.iter().collect();
+                kmers.sort_unstable();
+
+                for (kmer, count) in kmers {
+                    profile_writer.write_all(record.header().id)?;
+                    writeln!(profile_writer, "\t{}\t{}", decode_kmer(*kmer, k), count)?;
+                }
+            }
+
+            record_counts
+                .into_par_iter()
+                .reduce(KmerCounts::default, merge_counts)
+        }
+    };
+
+    *counts = merge_counts(std::mem::take(counts), batch_counts);
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn fasta_kmers(
+    fasta: Option<PathBuf>,
+    kmer_size: usize,
+    canonical: bool,
+    top: usize,
+    profiles: Option<PathBuf>,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    if !(1..=MAX_KMER_SIZE).contains(&kmer_size) {
+        return Err(AppError::InvalidArgError(format!(
+            "kmer size must be 1-{MAX_KMER_SIZE}"
+        )));
+    }
+
+    let reader = fastx_reader(fasta)?;
+
+    let mut profile_writer = match profiles {
+        Some(profiles) => Some(get_writer(Some(profiles), writer_opts)?),
+        None => None,
+    };
+
+    if let Some(profile_writer) = &mut profile_writer {
+        writeln!(profile_writer, "contig\tkmer\tcount")?;
+    }
+
+    let mut counts = KmerCounts::default();
+
+    for_each_batch(reader, |batch| {
+        process_batch(
+            batch,
+            kmer_size,
+            canonical,
+            &mut counts,
+            &mut profile_writer,
+        )
+    })?;
+
+    if let Some(profile_writer) = &mut profile_writer {
+        profile_writer.finish()?;
+    }
+
+    // Output file writer.
+    let mut writer = get_writer(outfile, writer_opts)?;
+
+    writeln!(writer, "# summary")?;
+    writeln!(writer, "total_kmers\tdistinct_kmers\tsingleton_kmers")?;
+    writeln!(
+        writer,
+        "{}\t{}\t{}",
+        counts.values().sum::<u64>(),
+        counts.len(),
+        counts.values().filter(|count| **count == 1).count()
+    )?;
+
+    writeln!(writer)?;
+    writeln!(writer, "# spectrum")?;
+    writeln!(writer, "count\tnum_kmers")?;
+
+    for (count, num_kmers) in spectrum(&counts) {
+        writeln!(writer, "{count}\t{num_kmers}")?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "# top_kmers")?;
+    writeln!(writer, "kmer\tcount")?;
+
+    for (kmer, count) in top_kmers(&counts, top) {
+        writeln!(writer, "{}\t{}", decode_kmer(kmer, kmer_size), count)?;
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(b"ACGT", 2, false, vec!["AC", "CG", "GT"])]
+#[case(b"acgNtac", 2, false, vec!["AC", "CG", "TA", "AC"])]
+#[case(b"AAAA", 5, false, vec![])]
+// GT is the reverse complement of AC.
+#[case(b"ACGT", 2, true, vec!["AC", "CG", "AC"])]
+#[case(b"TTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTG", 32, true, vec!["AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA", "CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"])]
+
+fn test_packed_kmers(
+    #[case] seq: &[u8],
+    #[case] k: usize,
+    #[case] canonical: bool,
+    #[case] expected: Vec<&str>,
+) {
+    let kmers: Vec<String> = PackedKmers::new(seq, k, canonical)
+        .map(|kmer| decode_kmer(kmer, k))
+        .collect();
+
+    assert_eq!(kmers, expected);
+}
+
+#[rstest]
+fn test_spectrum_and_top_kmers() {
+    let mut counts = KmerCounts::default();
+    add_kmers(&mut counts, b"AAAACGCG", 2, false);
+
+    assert_eq!(spectrum(&counts), BTreeMap::from([(1, 2), (2, 1), (3, 1)]));
+    assert_eq!(
+        top_kmers(&counts, 2)
+            .into_iter()
+            .map(|(kmer, count)| (decode_kmer(kmer, 2), count))
+            .collect::<Vec<(String, u64)>>(),
+        vec![("AA".to_string(), 3), ("CG".to_string(), 2)]
+    );
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod kmers;
+pub use kmers::fasta_kmers;
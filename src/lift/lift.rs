@@ -0,0 +1,65 @@
+use crate::args::LiftTarget;
+use crate::compress::map::{read_map, to_compressed, to_original};
+use crate::errors::AppError;
+use crate::fastx::reader::open_input;
+use crate::fastx::{WriterOpts, get_writer};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Maps BED intervals between original and homopolymer compressed coordinates, using
+/// the map written by `compress --map`. Columns after the end are kept as is.
+pub fn fasta_lift(
+    map: &Path,
+    bed: Option<PathBuf>,
+    to: &LiftTarget,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let map = read_map(map)?;
+
+    let reader = open_input(bed.as_deref())?;
+    let mut writer = get_writer(outfile, writer_opts)?;
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser")
+        {
+            writeln!(writer, "{line}")?;
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 {
+            return Err(AppError::InvalidArgError(format!(
+                "invalid BED line: {line}"
+            )));
+        }
+
+        let blocks = match map.get(fields[0]) {
+            Some(blocks) => blocks,
+            None => {
+                log::warn!("Skipping {}, it is not in the compression map", fields[0]);
+                continue;
+            }
+        };
+
+        let (start, end) = match to {
+            LiftTarget::Original => to_original(blocks, fields[1].parse()?, fields[2].parse()?),
+            LiftTarget::Compressed => to_compressed(blocks, fields[1].parse()?, fields[2].parse()?),
+        };
+
+        write!(writer, "{}\t{}\t{}", fields[0], start, end)?;
+        for field in &fields[3..] {
+            write!(writer, "\t{field}")?;
+        }
+        writeln!(writer)?;
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod lift;
+pub use lift::fasta_lift;
@@ -9,18 +9,35 @@ mod chunk;
 mod compress;
 mod dispatch;
 mod errors;
+mod expand;
 mod extract;
+mod fa2fq;
 mod fa2tab;
+mod fa2twobit;
+mod faidx;
+mod fastx;
 mod filter;
+mod fq2fa;
+mod gb2fa;
 mod grep;
 mod head;
 mod homopolymers;
+mod iupac;
+mod kmers;
+mod lift;
+mod mask;
+mod primers;
+mod repeats;
 mod reverse;
 mod sample;
 mod shuffle;
+mod sketch;
+mod sliding;
 mod sort;
 mod split;
 mod stats;
+mod twobit2fa;
+mod window;
 
 use args::App;
 use dispatch::dispatch;
@@ -39,6 +56,9 @@ fn main() {
 
     match result {
         Ok(_) => {}
-        Err(e) => error!("Error: {}", e),
+        Err(e) => {
+            error!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
 }
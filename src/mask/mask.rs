@@ -0,0 +1,321 @@
+use crate::args::MaskMode;
+use crate::errors::AppError;
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader, get_writer};
+use rstest::*;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Triplets are scored, so there are 64 possible words.
+const WORD_LEN: usize = 3;
+const NUM_WORDS: usize = 1 << (2 * WORD_LEN);
+
+#[inline]
+fn nt4(nt: u8) -> Option<usize> {
+    match nt {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+/// Perfect interval, i.e. a low-complexity interval none of whose sub-intervals
+/// has a higher score. The score is `r / l`.
+#[derive(Debug, Clone, Copy)]
+struct PerfectInterval {
+    start: usize,
+    end: usize,
+    r: usize,
+    l: usize,
+}
+
+/// Symmetric DUST (Morgulis et al. 2006), following the SDUST implementation
+/// in minimap2. Scores triplet repetitiveness in windows of `window` bases, and
+/// returns merged low-complexity intervals (BED coordinates) with a score above
+/// `threshold`. Non-ACGT bases split the sequence into independent pieces.
+pub struct Sdust {
+    threshold: usize,
+    window: usize,
+    /// Triplets of the current window.
+    words: VecDeque<usize>,
+    /// Triplet counts in the window, and in its suffix of length `suffix_len`.
+    window_counts: [usize; NUM_WORDS],
+    suffix_counts: [usize; NUM_WORDS],
+    window_score: usize,
+    suffix_score: usize,
+    suffix_len: usize,
+    perfect: Vec<PerfectInterval>,
+    masked: Vec<(usize, usize)>,
+}
+
+impl Sdust {
+    pub fn new(threshold: usize, window: usize) -> Self {
+        Sdust {
+            threshold,
+            window,
+            words: VecDeque::new(),
+            window_counts: [0; NUM_WORDS],
+            suffix_counts: [0; NUM_WORDS],
+            window_score: 0,
+            suffix_score: 0,
+            suffix_len: 0,
+            perfect: Vec::new(),
+            masked: Vec::new(),
+        }
+    }
+
+    fn reset_window(&mut self) {
+        self.words.clear();
+        self.window_counts = [0; NUM_WORDS];
+        self.suffix_counts = [0; NUM_WORDS];
+        self.window_score = 0;
+        self.suffix_score = 0;
+        self.suffix_len = 0;
+    }
+
+    fn shift_window(&mut self, word: usize) {
+        if self.words.len() + WORD_LEN > self.window {
+            let old = self.words.pop_front().unwrap();
+            self.window_counts[old] -= 1;
+            self.window_score -= self.window_counts[old];
+
+            if self.suffix_len > self.words.len() {
+                self.suffix_len -= 1;
+                self.suffix_counts[old] -= 1;
+                self.suffix_score -= self.suffix_counts[old];
+            }
+        }
+
+        self.words.push_back(word);
+        self.suffix_len += 1;
+        self.window_score += self.window_counts[word];
+        self.window_counts[word] += 1;
+        self.suffix_score += self.suffix_counts[word];
+        self.suffix_counts[word] += 1;
+
+        // Shrink the suffix until it is below the threshold again.
+        if self.suffix_counts[word] * 10 > 2 * self.threshold {
+            loop {
+                let old = self.words[self.words.len() - self.suffix_len];
+                self.suffix_counts[old] -= 1;
+                self.suffix_score -= self.suffix_counts[old];
+                self.suffix_len -= 1;
+
+                if old == word {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Saves the last perfect interval if it starts before the window at `start`, and drops
+    /// perfect intervals that have fallen out of the window.
+    fn save_masked(&mut self, start: usize) {
+        let last = match self.perfect.last() {
+            Some(last) if last.start < start => *last,
+            _ => return,
+        };
+
+        match self.masked.last_mut() {
+            // Merge if it overlaps with the last masked interval.
+            Some(masked) if last.start <= masked.1 => masked.1 = masked.1.max(last.end),
+            _ => self.masked.push((last.start, last.end)),
+        }
+
+        while self
+            .perfect
+            .last()
+            .is_some_and(|interval| interval.start < start)
+        {
+            self.perfect.pop();
+        }
+    }
+
+    fn find_perfect(&mut self, start: usize) {
+        let mut counts = self.suffix_counts;
+        let mut r = self.suffix_score;
+        let (mut max_r, mut max_l) = (0, 0);
+
+        for i in (0..self.words.len() - self.suffix_len).rev() {
+            let word = self.words[i];
+            r += counts[word];
+            counts[word] += 1;
+
+            let l = self.words.len() - i - 1;
+            if r * 10 <= self.threshold * l {
+                continue;
+            }
+
+            // Perfect intervals are sorted by decreasing start.
+            let mut j = 0;
+            while j < self.perfect.len() && self.perfect[j].start >= i + start {
+                let interval = &self.perfect[j];
+                if max_r == 0 || interval.r * max_l > max_r * interval.l {
+                    max_r = interval.r;
+                    max_l = interval.l;
+                }
+                j += 1;
+            }
+
+            if max_r == 0 || r * max_l >= max_r * l {
+                max_r = r;
+                max_l = l;
+                self.perfect.insert(
+                    j,
+                    PerfectInterval {
+                        start: i + start,
+                        end: self.words.len() + WORD_LEN - 1 + start,
+                        r,
+                        l,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn run(&mut self, seq: &[u8]) -> Vec<(usize, usize)> {
+        self.reset_window();
+        self.perfect.clear();
+        self.masked.clear();
+
+        // Length of the current ACGT stretch, and its last word.
+        let mut l: usize = 0;
+        let mut word: usize = 0;
+
+        for i in 0..=seq.len() {
+            match seq.get(i).and_then(|nt| nt4(*nt)) {
+                Some(b) => {
+                    l += 1;
+                    word = ((word << 2) | b) & (NUM_WORDS - 1);
+
+                    if l >= WORD_LEN {
+                        let start = l.saturating_sub(self.window) + (i + 1 - l);
+                        self.save_masked(start);
+                        self.shift_window(word);
+
+                        if self.window_score * 10 > self.suffix_len * self.threshold {
+                            self.find_perfect(start);
+                        }
+                    }
+                }
+                // Non-ACGT base or the end of the sequence.
+                None => {
+                    let mut start = (l + 1).saturating_sub(self.window) + (i + 1 - l);
+                    while !self.perfect.is_empty() {
+                        self.save_masked(start);
+                        start += 1;
+                    }
+
+                    self.reset_window();
+                    l = 0;
+                    word = 0;
+                }
+            }
+        }
+
+        std::mem::take(&mut self.masked)
+    }
+}
+
+fn apply_mask(seq: &[u8], intervals: &[(usize, usize)], mask_mode: &MaskMode) -> Vec<u8> {
+    let mut masked = seq.to_vec();
+
+    for &(start, end) in intervals {
+        for nt in &mut masked[start..end] {
+            *nt = match mask_mode {
+                MaskMode::Hard => b'N',
+                MaskMode::Soft => nt.to_ascii_lowercase(),
+            };
+        }
+    }
+
+    masked
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn fasta_mask(
+    fasta: Option<PathBuf>,
+    threshold: usize,
+    window: usize,
+    mask_mode: MaskMode,
+    bed: Option<PathBuf>,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    if window < WORD_LEN {
+        return Err(AppError::InvalidArgError(format!(
+            "window must be at least {WORD_LEN}"
+        )));
+    }
+
+    let mut reader = fastx_reader(fasta)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    let mut bed_writer = match bed {
+        Some(bed) => Some(get_writer(Some(bed), writer_opts)?),
+        None => None,
+    };
+
+    let mut sdust = Sdust::new(threshold, window);
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        let header = Header::parse(record.id());
+        let seq = record.seq();
+
+        let intervals = sdust.run(&seq);
+
+        writer.write(
+            &header,
+            &apply_mask(&seq, &intervals, &mask_mode),
+            record.qual(),
+        )?;
+
+        if let Some(bed_writer) = &mut bed_writer {
+            for (start, end) in &intervals {
+                bed_writer.write_all(header.id)?;
+                writeln!(bed_writer, "\t{start}\t{end}")?;
+            }
+        }
+    }
+
+    writer.finish()?;
+
+    if let Some(bed_writer) = &mut bed_writer {
+        bed_writer.finish()?;
+    }
+
+    Ok(())
+}
+
+#[rstest]
+#[case(b"", vec![])]
+#[case(b"ACGTTGCAAGTCCTAGGATC", vec![])]
+#[case(b"AAAAAAAAAAAAAAAAAAAA", vec![(0, 20)])]
+#[case(b"ACGTTGCAAGTCCAAAAAAAAAAAAAAAAAAAATAGGATC", vec![(13, 33)])]
+// N splits the sequence into independent pieces.
+#[case(b"AAAAAAAAAANAAAAAAAAAA", vec![(0, 10), (11, 21)])]
+#[case(b"CACACACACACACACACACACACACACACA", vec![(0, 30)])]
+
+fn test_sdust(#[case] seq: &[u8], #[case] expected: Vec<(usize, usize)>) {
+    assert_eq!(Sdust::new(20, 64).run(seq), expected);
+}
+
+#[rstest]
+#[case(b"ACGTACGT", vec![(2, 5)], MaskMode::Soft, b"ACgtaCGT")]
+#[case(b"ACGTACGT", vec![(0, 2), (6, 8)], MaskMode::Hard, b"NNGTACNN")]
+
+fn test_apply_mask(
+    #[case] seq: &[u8],
+    #[case] intervals: Vec<(usize, usize)>,
+    #[case] mask_mode: MaskMode,
+    #[case] expected: &[u8],
+) {
+    assert_eq!(apply_mask(seq, &intervals, &mask_mode), expected);
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod mask;
+pub use mask::fasta_mask;
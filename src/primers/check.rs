@@ -0,0 +1,390 @@
+use crate::amplicon::amplicon::search_both_strands;
+use crate::amplicon::primers::{PrimerPair, parse_primer_file};
+use crate::args::SearchType;
+use crate::errors::AppError;
+use crate::fastx::{WriterOpts, get_writer, read_records};
+use rayon::prelude::*;
+use rstest::*;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Monovalent salt (Na+) concentration in M used for melting temperatures.
+const NA_CONC: f64 = 0.05;
+
+/// Primer concentration in M used for melting temperatures.
+const PRIMER_CONC: f64 = 50e-9;
+
+/// Gas constant in cal/(K mol).
+const GAS_CONSTANT: f64 = 1.987;
+
+/// Minimum loop length of a hairpin.
+const MIN_LOOP_LEN: usize = 3;
+
+/// A single primer of the panel, named `<primer_name>_F` or `<primer_name>_R`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PanelPrimer {
+    name: String,
+    seq: Vec<u8>,
+    mismatch: Option<usize>,
+}
+
+/// Nearest neighbor enthalpy (kcal/mol) and entropy (cal/(K mol)) of a dinucleotide
+/// step, from the SantaLucia (1998) unified parameters.
+fn nearest_neighbor(step: &[u8]) -> Option<(f64, f64)> {
+    match step {
+        b"AA" | b"TT" => Some((-7.9, -22.2)),
+        b"AT" => Some((-7.2, -20.4)),
+        b"TA" => Some((-7.2, -21.3)),
+        b"CA" | b"TG" => Some((-8.5, -22.7)),
+        b"GT" | b"AC" => Some((-8.4, -22.4)),
+        b"CT" | b"AG" => Some((-7.8, -21.0)),
+        b"GA" | b"TC" => Some((-8.2, -22.2)),
+        b"CG" => Some((-10.6, -27.2)),
+        b"GC" => Some((-9.8, -24.4)),
+        b"GG" | b"CC" => Some((-8.0, -19.9)),
+        _ => None,
+    }
+}
+
+fn count_gc(seq: &[u8]) -> usize {
+    seq.iter()
+        .filter(|b| matches!(b.to_ascii_uppercase(), b'G' | b'C'))
+        .count()
+}
+
+/// GC content in percent.
+pub fn gc_content(seq: &[u8]) -> f64 {
+    match seq.is_empty() {
+        true => 0.0,
+        false => 100.0 * count_gc(seq) as f64 / seq.len() as f64,
+    }
+}
+
+/// Melting temperature in °C. Uses the nearest neighbor model with salt correction for
+/// primers of at least 14 bases, and the Wallace rule for shorter primers. Steps with
+/// ambiguous bases are ignored.
+pub fn melting_temperature(seq: &[u8]) -> f64 {
+    let seq = seq.to_ascii_uppercase();
+
+    if seq.len() < 14 {
+        let gc = count_gc(&seq);
+        let at = seq.iter().filter(|b| matches!(b, b'A' | b'T')).count();
+        return (2 * at + 4 * gc) as f64;
+    }
+
+    let mut enthalpy = 0.0;
+    let mut entropy = 0.0;
+
+    // Initiation with terminal G·C or A·T pair.
+    for terminal in [seq[0], seq[seq.len() - 1]] {
+        let (h, s) = match terminal {
+            b'G' | b'C' => (0.1, -2.8),
+            b'A' | b'T' => (2.3, 4.1),
+            _ => (0.0, 0.0),
+        };
+        enthalpy += h;
+        entropy += s;
+    }
+
+    for step in seq.windows(2) {
+        if let Some((h, s)) = nearest_neighbor(step) {
+            enthalpy += h;
+            entropy += s;
+        }
+    }
+
+    entropy += 0.368 * (seq.len() - 1) as f64 * NA_CONC.ln();
+
+    1000.0 * enthalpy / (entropy + GAS_CONSTANT * (PRIMER_CONC / 4.0).ln()) - 273.15
+}
+
+fn is_pair(a: u8, b: u8) -> bool {
+    matches!(
+        (a.to_ascii_uppercase(), b.to_ascii_uppercase()),
+        (b'A', b'T') | (b'T', b'A') | (b'C', b'G') | (b'G', b'C')
+    )
+}
+
+/// Longest run of consecutive base pairs when `a` and `b` bind antiparallel, and the
+/// longest run that includes the 3' terminal base of `a`, from which `a` can be extended.
+pub fn complementarity(a: &[u8], b: &[u8]) -> (usize, usize) {
+    // runs[j] holds the run ending at a[i - 1] paired with b[j], extended from a[i - 2], b[j + 1].
+    let mut runs: Vec<usize> = vec![0; b.len() + 1];
+    let mut longest = 0;
+
+    for &base in a {
+        let mut next: Vec<usize> = vec![0; b.len() + 1];
+        for (j, &other) in b.iter().enumerate() {
+            if is_pair(base, other) {
+                next[j] = runs[j + 1] + 1;
+                longest = longest.max(next[j]);
+            }
+        }
+        runs = next;
+    }
+
+    let three_prime = runs.iter().copied().max().unwrap_or(0);
+
+    (longest, three_prime)
+}
+
+/// Longest hairpin stem, i.e. consecutive base pairs of the primer with itself
+/// enclosing a loop of at least three bases.
+pub fn hairpin(seq: &[u8]) -> usize {
+    let mut longest = 0;
+
+    for i in 0..seq.len() {
+        for j in (i + MIN_LOOP_LEN + 1)..seq.len() {
+            let mut stem = 0;
+            while j - stem > i + stem + MIN_LOOP_LEN && is_pair(seq[i + stem], seq[j - stem]) {
+                stem += 1;
+            }
+            longest = longest.max(stem);
+        }
+    }
+
+    longest
+}
+
+/// Distinct primers of the panel, in primer file order.
+fn panel_primers(primer_pairs: &[PrimerPair]) -> Vec<PanelPrimer> {
+    let mut primers: Vec<PanelPrimer> = Vec::new();
+
+    for primer_pair in primer_pairs {
+        for (suffix, seq, mismatch) in [
+            (
+                "F",
+                &primer_pair.forward_primer,
+                primer_pair.forward_mismatch,
+            ),
+            (
+                "R",
+                &primer_pair.reverse_primer,
+                primer_pair.reverse_mismatch,
+            ),
+        ] {
+            let primer = PanelPrimer {
+                name: format!("{}_{}", primer_pair.primer_name, suffix),
+                seq: seq.to_ascii_uppercase(),
+                mismatch,
+            };
+
+            if !primers.contains(&primer) {
+                primers.push(primer);
+            }
+        }
+    }
+
+    primers
+}
+
+/// Every ordered combination of two panel primers as a primer pair, so that products of
+/// unintended combinations and of a single primer binding both strands are found as well.
+fn all_combinations(primers: &[PanelPrimer], min_len: usize, max_len: usize) -> Vec<PrimerPair> {
+    primers
+        .iter()
+        .flat_map(|forward| {
+            primers.iter().map(move |reverse| PrimerPair {
+                primer_name: format!("{}+{}", forward.name, reverse.name),
+                forward_primer: forward.seq.clone(),
+                reverse_primer: reverse.seq.clone(),
+                min_len,
+                max_len,
+                forward_mismatch: forward.mismatch,
+                reverse_mismatch: reverse.mismatch,
+            })
+        })
+        .collect()
+}
+
+/// Forward and reverse primer sequences of the intended primer pairs, in both orientations,
+/// since targets on the reverse strand are found as the `reverse+forward` combination.
+fn expected_combinations(primer_pairs: &[PrimerPair]) -> HashSet<(Vec<u8>, Vec<u8>)> {
+    primer_pairs
+        .iter()
+        .flat_map(|p| {
+            let forward = p.forward_primer.to_ascii_uppercase();
+            let reverse = p.reverse_primer.to_ascii_uppercase();
+
+            [(forward.clone(), reverse.clone()), (reverse, forward)]
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn fasta_primers_check(
+    primers: &Path,
+    background: Option<PathBuf>,
+    search_type: &SearchType,
+    min_len: usize,
+    max_len: usize,
+    min_dimer_len: usize,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let primer_pairs = parse_primer_file(primers, min_len, max_len)?;
+    let panel = panel_primers(&primer_pairs);
+
+    let mut writer = get_writer(outfile, writer_opts)?;
+
+    writeln!(writer, "# primers")?;
+    writeln!(
+        writer,
+        "primer\tsequence\tlength\tgc\ttm\tself_dimer\tself_3p_dimer\thairpin"
+    )?;
+
+    for primer in &panel {
+        let (self_dimer, self_3p_dimer) = complementarity(&primer.seq, &primer.seq);
+
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{:.1}\t{:.1}\t{}\t{}\t{}",
+            primer.name,
+            String::from_utf8_lossy(&primer.seq),
+            primer.seq.len(),
+            gc_content(&primer.seq),
+            melting_temperature(&primer.seq),
+            self_dimer,
+            self_3p_dimer,
+            hairpin(&primer.seq)
+        )?;
+    }
+
+    writeln!(writer)?;
+    writeln!(writer, "# dimers")?;
+    writeln!(writer, "primer_a\tprimer_b\tdimer\t3p_dimer")?;
+
+    for (i, a) in panel.iter().enumerate() {
+        for b in &panel[i + 1..] {
+            let (dimer, a_3p) = complementarity(&a.seq, &b.seq);
+            let (_, b_3p) = complementarity(&b.seq, &a.seq);
+            let three_prime = a_3p.max(b_3p);
+
+            if three_prime >= min_dimer_len {
+                writeln!(writer, "{}\t{}\t{}\t{}", a.name, b.name, dimer, three_prime)?;
+            }
+        }
+    }
+
+    if let Some(background) = background {
+        let records = read_records(Some(background))?;
+
+        let combinations = all_combinations(&panel, min_len, max_len);
+        let expected = expected_combinations(&primer_pairs);
+
+        writeln!(writer)?;
+        writeln!(writer, "# off_targets")?;
+        writeln!(
+            writer,
+            "sequence_id\tproduct\tstart\tend\tinsert_length\tforward_mismatches\treverse_mismatches\texpected_pair"
+        )?;
+
+        // Since all ordered combinations are searched, each product is found once on the
+        // forward strand, with the product name giving the primer orientation.
+        let products: Vec<_> = records
+            .par_iter()
+            .map(|record| search_both_strands(&record.seq, &combinations, search_type, true, false))
+            .collect();
+
+        for (record, amplicons) in records.iter().zip(products) {
+            for (i, amplicon) in amplicons {
+                let combination = &combinations[i];
+
+                let is_expected = expected.contains(&(
+                    combination.forward_primer.clone(),
+                    combination.reverse_primer.clone(),
+                ));
+
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    String::from_utf8_lossy(record.header().id),
+                    combination.primer_name,
+                    amplicon.start,
+                    amplicon.end,
+                    amplicon.insert_length,
+                    amplicon.mismatches.forward,
+                    amplicon.mismatches.reverse,
+                    is_expected
+                )?;
+            }
+        }
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(b"ACGT", b"TTGG", true)]
+#[case(b"TTGG", b"ACGT", true)]
+#[case(b"acgt", b"ttgg", true)]
+#[case(b"ACGT", b"ACGT", false)]
+#[case(b"ACGT", b"GGCC", false)]
+
+fn test_expected_combinations(
+    #[case] forward: &[u8],
+    #[case] reverse: &[u8],
+    #[case] expected: bool,
+) {
+    let primer_pairs = vec![PrimerPair {
+        primer_name: "p1".to_string(),
+        forward_primer: b"ACGT".to_vec(),
+        reverse_primer: b"TTGG".to_vec(),
+        min_len: 0,
+        max_len: 2000,
+        forward_mismatch: None,
+        reverse_mismatch: None,
+    }];
+
+    assert_eq!(
+        expected_combinations(&primer_pairs)
+            .contains(&(forward.to_ascii_uppercase(), reverse.to_ascii_uppercase())),
+        expected
+    );
+}
+
+#[rstest]
+#[case(b"", 0.0)]
+#[case(b"ATAT", 0.0)]
+#[case(b"GCAT", 50.0)]
+#[case(b"gcgc", 100.0)]
+
+fn test_gc_content(#[case] seq: &[u8], #[case] expected: f64) {
+    assert_eq!(gc_content(seq), expected);
+}
+
+#[rstest]
+// Wallace rule.
+#[case(b"AGCTAGCTAGCT", 36.0)]
+// Nearest neighbor model.
+#[case(b"CAGGAAACAGCTATGAC", 43.6)]
+#[case(b"GTAAAACGACGGCCAGT", 49.2)]
+
+fn test_melting_temperature(#[case] seq: &[u8], #[case] expected: f64) {
+    assert_eq!((melting_temperature(seq) * 10.0).round() / 10.0, expected);
+}
+
+#[rstest]
+#[case(b"AAAA", b"GGGG", (0, 0))]
+#[case(b"AGCTAGCTAGCT", b"AGCTAGCTAGCT", (12, 12))]
+// The 3' end of a (CCG) binds within b, which cannot be extended from this dimer.
+#[case(b"TTTTCCG", b"TTCGGTT", (3, 3))]
+#[case(b"TTCGGTT", b"TTTTCCG", (3, 0))]
+#[case(b"ACGTTTTT", b"GGGGACGT", (4, 1))]
+
+fn test_complementarity(#[case] a: &[u8], #[case] b: &[u8], #[case] expected: (usize, usize)) {
+    assert_eq!(complementarity(a, b), expected);
+}
+
+#[rstest]
+#[case(b"AAAAAAAA", 0)]
+#[case(b"GGGCCAAAAGGCCCAT", 5)]
+// The loop needs at least three bases.
+#[case(b"GGCCGGCC", 2)]
+
+fn test_hairpin(#[case] seq: &[u8], #[case] expected: usize) {
+    assert_eq!(hairpin(seq), expected);
+}
@@ -0,0 +1,2 @@
+pub mod check;
+pub use check::fasta_primers_check;
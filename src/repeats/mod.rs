@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod repeats;
+pub use repeats::fasta_repeats;
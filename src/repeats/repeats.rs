@@ -0,0 +1,322 @@
+use crate::errors::AppError;
+use crate::fastx::{WriterOpts, fastx_reader, get_writer};
+use rstest::*;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Tandem repeat in BED coordinates.
+#[derive(Debug, PartialEq)]
+pub struct TandemRepeat {
+    pub start: usize,
+    pub end: usize,
+    pub motif: Vec<u8>,
+    pub mismatches: usize,
+}
+
+impl TandemRepeat {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn copies(&self) -> f64 {
+        self.len() as f64 / self.motif.len() as f64
+    }
+
+    /// Fraction of bases matching the motif.
+    pub fn purity(&self) -> f64 {
+        1.0 - self.mismatches as f64 / self.len() as f64
+    }
+}
+
+#[inline]
+fn is_acgt(nt: u8) -> bool {
+    matches!(nt.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T')
+}
+
+#[inline]
+fn same_base(a: u8, b: u8) -> bool {
+    a.eq_ignore_ascii_case(&b)
+}
+
+/// A motif is primitive if it is not a repeat of a shorter motif (e.g. ATAT of AT),
+/// which would report the same repeat again with a longer period.
+fn is_primitive(motif: &[u8]) -> bool {
+    (1..motif.len())
+        .filter(|period| motif.len().is_multiple_of(*period))
+        .all(|period| {
+            !motif
+                .iter()
+                .enumerate()
+                .all(|(i, nt)| same_base(*nt, motif[i % period]))
+        })
+}
+
+/// Minimum number of copies for a period, where the last value applies to longer periods.
+fn min_copies_for(min_copies: &[usize], period: usize) -> usize {
+    min_copies
+        .get(period - 1)
+        .or(min_copies.last())
+        .copied()
+        .unwrap_or(1)
+}
+
+/// Extends a repeat of the motif starting at `start`. With `imperfect`, a substitution is
+/// allowed if it is followed by a full copy of the motif, so repeats never end with a mismatch.
+/// Returns the end of the repeat and the positions of the substitutions.
+fn extend_repeat(seq: &[u8], start: usize, period: usize, imperfect: bool) -> (usize, Vec<usize>) {
+    let motif = &seq[start..start + period];
+    let matches_motif = |k: usize| same_base(seq[k], motif[(k - start) % period]);
+
+    let mut j = start + period;
+    let mut substitutions: Vec<usize> = Vec::new();
+
+    while j < seq.len() {
+        if matches_motif(j) {
+            j += 1;
+            continue;
+        }
+
+        let next_copy = j + 1..j + 1 + period;
+        if imperfect
+            && is_acgt(seq[j])
+            && next_copy.end <= seq.len()
+            && next_copy.clone().all(matches_motif)
+        {
+            substitutions.push(j);
+            j = next_copy.end;
+            continue;
+        }
+
+        break;
+    }
+
+    (j, substitutions)
+}
+
+/// A scanned repeat, reused for later starts within it. A start whose motif copy contains no
+/// substitution extends to the same end, with the substitutions after the start.
+struct ScannedRun {
+    end: usize,
+    substitutions: Vec<usize>,
+}
+
+impl ScannedRun {
+    /// Number of substitutions of the repeat from `start`, or `None` if extending from
+    /// `start` may give a different repeat.
+    fn mismatches_from(&self, start: usize, period: usize) -> Option<usize> {
+        if start + period > self.end {
+            return None;
+        }
+
+        let i = self.substitutions.partition_point(|k| *k < start);
+
+        match self.substitutions.get(i) {
+            Some(k) if *k < start + period => None,
+            _ => Some(self.substitutions.len() - i),
+        }
+    }
+}
+
+pub fn find_repeats(
+    seq: &[u8],
+    period: usize,
+    min_copies: usize,
+    imperfect: bool,
+    min_purity: f64,
+) -> Vec<TandemRepeat> {
+    let mut repeats: Vec<TandemRepeat> = Vec::new();
+
+    if period == 0 {
+        return repeats;
+    }
+
+    // Without it, a long repeat that fails the purity threshold is rescanned from every start.
+    let mut scanned: Option<ScannedRun> = None;
+
+    let mut i = 0;
+
+    while i + period <= seq.len() {
+        let motif = &seq[i..i + period];
+
+        if !motif.iter().all(|nt| is_acgt(*nt)) || !is_primitive(motif) {
+            i += 1;
+            continue;
+        }
+
+        let reused = scanned.as_ref().and_then(|run| {
+            run.mismatches_from(i, period)
+                .map(|mismatches| (run.end, mismatches))
+        });
+
+        let (j, mismatches) = match reused {
+            Some(reused) => reused,
+            None => {
+                let (j, substitutions) = extend_repeat(seq, i, period, imperfect);
+                let mismatches = substitutions.len();
+
+                // Starts covering a substitution of the scanned run only give short repeats,
+                // so they do not replace it.
+                if scanned.as_ref().is_none_or(|run| i >= run.end) {
+                    scanned = Some(ScannedRun {
+                        end: j,
+                        substitutions,
+                    });
+                }
+
+                (j, mismatches)
+            }
+        };
+
+        let repeat = TandemRepeat {
+            start: i,
+            end: j,
+            motif: motif.to_ascii_uppercase(),
+            mismatches,
+        };
+
+        // We have a repeat with the required number of copies.
+        if repeat.len() >= min_copies * period && repeat.purity() >= min_purity {
+            // Starting within the repeat would only give a rotation of the same motif.
+            i = j - period + 1;
+            repeats.push(repeat);
+        } else {
+            i += 1;
+        }
+    }
+
+    repeats
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn fasta_repeats(
+    fasta: Option<PathBuf>,
+    min_period: usize,
+    max_period: usize,
+    min_copies: Vec<usize>,
+    imperfect: bool,
+    min_purity: f64,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    if min_period == 0 || min_period > max_period {
+        return Err(AppError::InvalidArgError(format!(
+            "invalid period range {min_period}-{max_period}"
+        )));
+    }
+
+    let mut reader = fastx_reader(fasta)?;
+
+    // Output file writer.
+    let mut writer = get_writer(outfile, writer_opts)?;
+
+    // Write tsv header.
+    writeln!(
+        writer,
+        "contig\tstart\tend\tlen\tperiod\tmotif\tcopies\tpurity"
+    )?;
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        let seq = record.seq();
+
+        let mut repeats: Vec<TandemRepeat> = (min_period..=max_period)
+            .flat_map(|period| {
+                find_repeats(
+                    &seq,
+                    period,
+                    min_copies_for(&min_copies, period),
+                    imperfect,
+                    min_purity,
+                )
+            })
+            .collect();
+
+        repeats.sort_by_key(|repeat| (repeat.start, repeat.motif.len()));
+
+        for repeat in repeats {
+            writer.write_all(record.id())?;
+            writeln!(
+                writer,
+                "\t{}\t{}\t{}\t{}\t{}\t{:.1}\t{:.3}",
+                repeat.start,
+                repeat.end,
+                repeat.len(),
+                repeat.motif.len(),
+                String::from_utf8_lossy(&repeat.motif),
+                repeat.copies(),
+                repeat.purity()
+            )?;
+        }
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(b"A", true)]
+#[case(b"AT", true)]
+#[case(b"AA", false)]
+#[case(b"ATAT", false)]
+#[case(b"ATG", true)]
+#[case(b"ACGACG", false)]
+
+fn test_is_primitive(#[case] motif: &[u8], #[case] expected: bool) {
+    assert_eq!(is_primitive(motif), expected);
+}
+
+#[rstest]
+#[case(b"GGATATATATCC", 2, 3, false, vec![(2, 10, b"AT".to_vec(), 0)])]
+// Partial copies at the end are included.
+#[case(b"TTCAGCAGCAGCT", 3, 3, false, vec![(2, 12, b"CAG".to_vec(), 0)])]
+#[case(b"ttcagcagcagct", 3, 3, false, vec![(2, 12, b"CAG".to_vec(), 0)])]
+// The leftmost rotation of the motif is reported.
+#[case(b"GGCAGCAGCAGCC", 3, 3, false, vec![(1, 12, b"GCA".to_vec(), 0)])]
+#[case(b"GGATATATATCC", 2, 5, false, vec![])]
+// Homopolymers are not reported as dinucleotide repeats.
+#[case(b"AAAAAAAA", 2, 2, false, vec![])]
+#[case(b"NNNNNNNN", 1, 2, false, vec![])]
+// Imperfect repeats need a full copy after each substitution.
+#[case(b"CAGCAGCTGCAGCAG", 3, 4, false, vec![])]
+#[case(b"CAGCAGCTGCAGCAG", 3, 4, true, vec![(0, 15, b"CAG".to_vec(), 1)])]
+#[case(b"CAGCAGCAGCT", 3, 3, true, vec![(0, 10, b"CAG".to_vec(), 0)])]
+
+fn test_find_repeats(
+    #[case] seq: &[u8],
+    #[case] period: usize,
+    #[case] min_copies: usize,
+    #[case] imperfect: bool,
+    #[case] expected: Vec<(usize, usize, Vec<u8>, usize)>,
+) {
+    let repeats: Vec<(usize, usize, Vec<u8>, usize)> =
+        find_repeats(seq, period, min_copies, imperfect, 0.0)
+            .into_iter()
+            .map(|repeat| (repeat.start, repeat.end, repeat.motif, repeat.mismatches))
+            .collect();
+
+    assert_eq!(repeats, expected);
+}
+
+#[rstest]
+#[case(b"CAGCTGCAGCAGCAGCAGCAG", 0.9, vec![(0, 21, b"CAG".to_vec(), 1)])]
+// The impure start is dropped, keeping the part of the repeat after the substitution.
+#[case(b"CAGCTGCAGCAGCAGCAGCAG", 0.99, vec![(5, 21, b"GCA".to_vec(), 0)])]
+#[case(b"CAGCTGCAGCTGCAG", 0.99, vec![])]
+
+fn test_find_repeats_min_purity(
+    #[case] seq: &[u8],
+    #[case] min_purity: f64,
+    #[case] expected: Vec<(usize, usize, Vec<u8>, usize)>,
+) {
+    let repeats: Vec<(usize, usize, Vec<u8>, usize)> = find_repeats(seq, 3, 4, true, min_purity)
+        .into_iter()
+        .map(|repeat| (repeat.start, repeat.end, repeat.motif, repeat.mismatches))
+        .collect();
+
+    assert_eq!(repeats, expected);
+}
@@ -1,32 +1,138 @@
-use needletail::Sequence;
-
+use crate::args::ReverseMode;
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
+#[cfg(test)]
+use crate::fastx::writer::write_temp_file;
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader};
+use crate::iupac::complement_base;
+use rstest::*;
+use std::collections::HashSet;
+use std::fs::File;
+#[cfg(test)]
+use std::io::Write;
+use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
 
-pub fn fasta_reverse(fasta: Option<PathBuf>, outfile: Option<PathBuf>) -> Result<(), AppError> {
-    let mut reader = needletail_reader(fasta)?;
-    let mut writer = get_bufwriter(outfile)?;
+fn apply_mode(seq: &[u8], mode: &ReverseMode) -> Vec<u8> {
+    match mode {
+        ReverseMode::Reverse => seq.iter().rev().copied().collect(),
+        ReverseMode::Complement => seq.iter().map(|nt| complement_base(*nt)).collect(),
+        ReverseMode::ReverseComplement => seq.iter().rev().map(|nt| complement_base(*nt)).collect(),
+    }
+}
+
+/// Qualities follow the bases, so they are reversed but never complemented.
+fn reverse_qual(qual: &[u8], mode: &ReverseMode) -> Vec<u8> {
+    match mode {
+        ReverseMode::Complement => qual.to_vec(),
+        ReverseMode::Reverse | ReverseMode::ReverseComplement => {
+            qual.iter().rev().copied().collect()
+        }
+    }
+}
+
+fn mode_tag(mode: &ReverseMode) -> &'static [u8] {
+    match mode {
+        ReverseMode::Reverse => b"rev",
+        ReverseMode::Complement => b"comp",
+        ReverseMode::ReverseComplement => b"rc",
+    }
+}
+
+/// One id per line, everything after the first whitespace is ignored.
+fn read_id_file(ids: &PathBuf) -> Result<HashSet<Vec<u8>>, AppError> {
+    let reader = BufReader::new(File::open(ids)?);
+
+    let lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
+
+    let id_set: HashSet<Vec<u8>> = lines
+        .iter()
+        .filter_map(|line| {
+            line.split_whitespace()
+                .next()
+                .map(|id| id.as_bytes().to_vec())
+        })
+        .collect();
+
+    Ok(id_set)
+}
+
+pub fn fasta_reverse(
+    fasta: Option<PathBuf>,
+    mode: ReverseMode,
+    tag: bool,
+    ids: Option<PathBuf>,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let id_set = ids.as_ref().map(read_id_file).transpose()?;
+
+    let mut reader = fastx_reader(fasta)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
 
     while let Some(record) = reader.next() {
-        match record {
-            Ok(record) => {
-                // Id.
-                writer.write_all(b">")?;
-                writer.write_all(record.id())?;
-
-                // Rev complement seq.
-                writer.write_all(b"\n")?;
-                writer.write_all(&record.reverse_complement()[..])?;
-                writer.write_all(b"\n")?;
-            }
-            Err(_) => {
-                continue;
-            }
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        let header = Header::parse(record.id());
+
+        let selected = match &id_set {
+            Some(id_set) => id_set.contains(header.id),
+            None => true,
         };
+
+        if !selected {
+            writer.write(&header, &record.seq(), record.qual())?;
+            continue;
+        }
+
+        let seq = apply_mode(&record.seq(), &mode);
+        let qual = record.qual().map(|qual| reverse_qual(qual, &mode));
+
+        match tag {
+            true => {
+                let id = [header.id, b"|", mode_tag(&mode)].concat();
+                writer.write(&header.with_id(&id), &seq, qual.as_deref())?;
+            }
+            false => writer.write(&header, &seq, qual.as_deref())?,
+        }
     }
 
-    writer.flush()?;
+    writer.finish()?;
 
     Ok(())
 }
+
+#[rstest]
+#[case(b"", ReverseMode::ReverseComplement, b"")]
+#[case(b"AACG", ReverseMode::Reverse, b"GCAA")]
+#[case(b"AACG", ReverseMode::Complement, b"TTGC")]
+#[case(b"AACG", ReverseMode::ReverseComplement, b"CGTT")]
+#[case(b"acgtN", ReverseMode::ReverseComplement, b"Nacgt")]
+#[case(b"RYKMBVDHSWN", ReverseMode::Complement, b"YRMKVBHDSWN")]
+#[case(b"rykmbvdh", ReverseMode::Complement, b"yrmkvbhd")]
+#[case(b"AC-GT", ReverseMode::ReverseComplement, b"AC-GT")]
+
+fn test_apply_mode(#[case] seq: &[u8], #[case] mode: ReverseMode, #[case] expected: &[u8]) {
+    assert_eq!(&apply_mode(seq, &mode)[..], expected);
+}
+
+#[rstest]
+#[case(b"seq_1\nseq_2 desc\n\n", Some(vec![b"seq_1".to_vec(), b"seq_2".to_vec()]))]
+#[case(b"seq_1\n\xff\nseq_2\n", None)]
+
+fn test_read_id_file(#[case] content: &'static [u8], #[case] expected: Option<Vec<Vec<u8>>>) {
+    let (_dir, path) = write_temp_file("ids.txt", None, |writer| {
+        writer.write_all(content)?;
+        Ok(())
+    });
+
+    let ids = read_id_file(&path).ok().map(|ids| {
+        let mut ids: Vec<Vec<u8>> = ids.into_iter().collect();
+        ids.sort();
+        ids
+    });
+
+    assert_eq!(ids, expected);
+}
@@ -1,6 +1,5 @@
 use crate::errors::AppError;
-use bio::io::fasta::Record;
-use bio_utils_rs::io::{bio_fasta_reader, bio_fasta_writer};
+use crate::fastx::{FastxWriter, OwnedRecord, WriterOpts, read_records};
 use rand::{prelude::*, rng};
 use std::path::PathBuf;
 
@@ -8,11 +7,11 @@ pub fn fasta_sample(
     fasta: Option<PathBuf>,
     by: f32,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
-    let reader = bio_fasta_reader(fasta)?;
-    let mut writer = bio_fasta_writer(outfile)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
 
-    let fasta_records: Vec<Record> = reader.records().filter_map(|record| record.ok()).collect();
+    let fasta_records: Vec<OwnedRecord> = read_records(fasta)?;
 
     // Randomly choose records to keep.
 
@@ -30,10 +29,10 @@ pub fn fasta_sample(
     let sample = fasta_records.choose_multiple(&mut rng, sample_by);
 
     for r in sample {
-        writer.write_record(r)?;
+        writer.write(&r.header(), &r.seq, r.qual())?;
     }
 
-    writer.flush()?;
+    writer.finish()?;
 
     Ok(())
 }
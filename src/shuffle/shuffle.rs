@@ -1,25 +1,26 @@
 use crate::errors::AppError;
-use bio::io::fasta::Record;
-use bio_utils_rs::io::{bio_fasta_reader, bio_fasta_writer};
+use crate::fastx::{FastxWriter, OwnedRecord, WriterOpts, read_records};
 use rand::{prelude::*, rng};
 use std::path::PathBuf;
 
-pub fn fasta_shuffle(fasta: Option<PathBuf>, outfile: Option<PathBuf>) -> Result<(), AppError> {
-    let reader = bio_fasta_reader(fasta)?;
-    let mut writer = bio_fasta_writer(outfile)?;
+pub fn fasta_shuffle(
+    fasta: Option<PathBuf>,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
 
-    let mut fasta_records: Vec<Record> =
-        reader.records().filter_map(|record| record.ok()).collect();
+    let mut fasta_records: Vec<OwnedRecord> = read_records(fasta)?;
 
     // Shuffle records.
     let mut rng = rng();
     fasta_records.shuffle(&mut rng);
 
     for r in fasta_records {
-        writer.write_record(&r)?;
+        writer.write(&r.header(), &r.seq, r.qual())?;
     }
 
-    writer.flush()?;
+    writer.finish()?;
 
     Ok(())
 }
@@ -0,0 +1,215 @@
+use crate::args::DistFormat;
+use crate::errors::AppError;
+use crate::fastx::{OutputWriter, WriterOpts, get_writer};
+use crate::sketch::sketch::{Sketch, SketchParams, SketchScheme, read_sketches};
+use rayon::prelude::*;
+use rstest::*;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Debug, PartialEq)]
+struct Distance {
+    shared: usize,
+    union: usize,
+    jaccard: f64,
+    distance: f64,
+}
+
+impl Distance {
+    /// ANI estimate in percent.
+    fn ani(&self) -> f64 {
+        100.0 * (1.0 - self.distance)
+    }
+}
+
+/// Number of shared hashes and size of the union of two sorted hash lists. With `max_union`,
+/// only the smallest `max_union` hashes of the union are considered (MinHash).
+fn shared_and_union(a: &[u64], b: &[u64], max_union: Option<usize>) -> (usize, usize) {
+    let max_union = max_union.unwrap_or(usize::MAX);
+
+    let (mut i, mut j) = (0, 0);
+    let (mut shared, mut union) = (0, 0);
+
+    while union < max_union && (i < a.len() || j < b.len()) {
+        match (a.get(i), b.get(j)) {
+            (Some(x), Some(y)) if x == y => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+            (Some(x), Some(y)) if x < y => i += 1,
+            (Some(_), None) => i += 1,
+            _ => j += 1,
+        }
+
+        union += 1;
+    }
+
+    (shared, union)
+}
+
+/// Mash distance from the Jaccard index of k-mer sets (Ondov et al. 2016), capped at 1.
+fn mash_distance(jaccard: f64, kmer_size: usize) -> f64 {
+    match jaccard {
+        0.0 => 1.0,
+        1.0 => 0.0,
+        jaccard => (-(2.0 * jaccard / (1.0 + jaccard)).ln() / kmer_size as f64).min(1.0),
+    }
+}
+
+fn distance(a: &Sketch, b: &Sketch, params: &SketchParams) -> Distance {
+    let max_union = match params.scheme {
+        SketchScheme::MinHash { size } => Some(size),
+        SketchScheme::FracMinHash { .. } => None,
+    };
+
+    let (shared, union) = shared_and_union(&a.hashes, &b.hashes, max_union);
+
+    let jaccard = match union {
+        0 => 0.0,
+        union => shared as f64 / union as f64,
+    };
+
+    Distance {
+        shared,
+        union,
+        jaccard,
+        distance: mash_distance(jaccard, params.kmer_size),
+    }
+}
+
+fn write_tsv(
+    writer: &mut OutputWriter,
+    sketches: &[Sketch],
+    distances: &[((usize, usize), Distance)],
+) -> Result<(), AppError> {
+    writeln!(
+        writer,
+        "query\treference\tshared_hashes\tunion_hashes\tjaccard\tdistance\tani"
+    )?;
+
+    for ((i, j), distance) in distances {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{:.6}\t{:.6}\t{:.2}",
+            sketches[*i].name,
+            sketches[*j].name,
+            distance.shared,
+            distance.union,
+            distance.jaccard,
+            distance.distance,
+            distance.ani()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Square (relaxed) PHYLIP distance matrix, with tab separated names and distances.
+fn write_phylip(
+    writer: &mut OutputWriter,
+    sketches: &[Sketch],
+    distances: &[((usize, usize), Distance)],
+) -> Result<(), AppError> {
+    let mut matrix: Vec<Vec<f64>> = vec![vec![0.0; sketches.len()]; sketches.len()];
+
+    for ((i, j), distance) in distances {
+        matrix[*i][*j] = distance.distance;
+        matrix[*j][*i] = distance.distance;
+    }
+
+    writeln!(writer, "{}", sketches.len())?;
+
+    for (sketch, row) in sketches.iter().zip(matrix) {
+        write!(writer, "{}", sketch.name)?;
+        for distance in row {
+            write!(writer, "\t{distance:.6}")?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+pub fn fasta_dist(
+    sketches: Vec<PathBuf>,
+    output_format: &DistFormat,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let mut params: Option<SketchParams> = None;
+    let mut all_sketches: Vec<Sketch> = Vec::new();
+
+    for path in &sketches {
+        let (file_params, file_sketches) = read_sketches(path)?;
+
+        // Hashes are only comparable with the same k-mer size and scheme.
+        if params.is_some_and(|params| params != file_params) {
+            return Err(AppError::InvalidArgError(format!(
+                "sketch parameters of {} do not match previous sketch files",
+                path.display()
+            )));
+        }
+
+        params = Some(file_params);
+        all_sketches.extend(file_sketches);
+    }
+
+    let params = match params {
+        Some(params) => params,
+        None => return Err(AppError::InvalidArgError("no sketch files".into())),
+    };
+
+    let n = all_sketches.len();
+
+    let distances: Vec<((usize, usize), Distance)> = (0..n)
+        .into_par_iter()
+        .flat_map_iter(|i| (i + 1..n).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            (
+                (i, j),
+                distance(&all_sketches[i], &all_sketches[j], &params),
+            )
+        })
+        .collect();
+
+    let mut writer = get_writer(outfile, writer_opts)?;
+
+    match output_format {
+        DistFormat::Tsv => write_tsv(&mut writer, &all_sketches, &distances)?,
+        DistFormat::Phylip => write_phylip(&mut writer, &all_sketches, &distances)?,
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(&[1, 2, 3], &[2, 3, 4], None, (2, 4))]
+#[case(&[1, 2, 3], &[2, 3, 4], Some(3), (2, 3))]
+#[case(&[1, 2, 3], &[2, 3, 4], Some(1), (0, 1))]
+#[case(&[1, 2], &[], None, (0, 2))]
+#[case(&[], &[], Some(2), (0, 0))]
+
+fn test_shared_and_union(
+    #[case] a: &[u64],
+    #[case] b: &[u64],
+    #[case] max_union: Option<usize>,
+    #[case] expected: (usize, usize),
+) {
+    assert_eq!(shared_and_union(a, b, max_union), expected);
+}
+
+#[rstest]
+#[case(1.0, 21, 0.0)]
+#[case(0.0, 21, 1.0)]
+#[case(0.5, 21, 0.0193)]
+#[case(1e-30, 1, 1.0)]
+
+fn test_mash_distance(#[case] jaccard: f64, #[case] kmer_size: usize, #[case] expected: f64) {
+    assert_eq!(
+        (mash_distance(jaccard, kmer_size) * 1e4).round() / 1e4,
+        expected
+    );
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod sketch;
+pub use sketch::fasta_sketch;
+
+pub mod dist;
+pub use dist::fasta_dist;
@@ -0,0 +1,324 @@
+use crate::errors::AppError;
+use crate::fastx::reader::open_input;
+use crate::fastx::{OutputWriter, WriterOpts, fastx_reader, get_writer, read_records};
+use crate::kmers::kmers::{MAX_KMER_SIZE, PackedKmers, hash_kmer};
+#[cfg(test)]
+use crate::{args::CompressType, fastx::writer::write_temp_file};
+use rayon::prelude::*;
+use rstest::*;
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SketchScheme {
+    /// The `size` smallest hashes (bottom-s MinHash).
+    MinHash { size: usize },
+    /// All hashes below `u64::MAX / scaled`, so sketch size scales with sequence size.
+    FracMinHash { scaled: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SketchParams {
+    pub kmer_size: usize,
+    pub scheme: SketchScheme,
+}
+
+/// Sorted hashes of the canonical k-mers of a record or file.
+#[derive(Debug, PartialEq)]
+pub struct Sketch {
+    pub name: String,
+    pub length: usize,
+    pub hashes: Vec<u64>,
+}
+
+struct SketchBuilder {
+    params: SketchParams,
+    length: usize,
+    hashes: BTreeSet<u64>,
+}
+
+impl SketchBuilder {
+    fn new(params: SketchParams) -> Self {
+        SketchBuilder {
+            params,
+            length: 0,
+            hashes: BTreeSet::new(),
+        }
+    }
+
+    fn insert(&mut self, hash: u64) {
+        match self.params.scheme {
+            SketchScheme::MinHash { size } => {
+                let is_candidate = self.hashes.len() < size
+                    || self.hashes.last().is_some_and(|largest| hash < *largest);
+
+                if is_candidate && self.hashes.insert(hash) && self.hashes.len() > size {
+                    self.hashes.pop_last();
+                }
+            }
+            SketchScheme::FracMinHash { scaled } => {
+                if hash <= u64::MAX / scaled {
+                    self.hashes.insert(hash);
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, seq: &[u8]) {
+        self.length += seq.len();
+
+        for kmer in PackedKmers::new(seq, self.params.kmer_size, true) {
+            self.insert(hash_kmer(kmer));
+        }
+    }
+
+    fn build(self, name: String) -> Sketch {
+        Sketch {
+            name,
+            length: self.length,
+            hashes: self.hashes.into_iter().collect(),
+        }
+    }
+}
+
+fn sketch_records(fasta: &Path, params: SketchParams) -> Result<Vec<Sketch>, AppError> {
+    let records = read_records(Some(fasta.to_path_buf()))?;
+
+    let sketches: Vec<Sketch> = records
+        .par_iter()
+        .map(|record| {
+            let mut builder = SketchBuilder::new(params);
+            builder.add(&record.seq);
+            builder.build(String::from_utf8_lossy(record.header().id).into_owned())
+        })
+        .collect();
+
+    Ok(sketches)
+}
+
+fn sketch_file(fasta: &Path, params: SketchParams) -> Result<Sketch, AppError> {
+    let mut reader = fastx_reader(Some(fasta.to_path_buf()))?;
+    let mut builder = SketchBuilder::new(params);
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        builder.add(&record.seq());
+    }
+
+    Ok(builder.build(fasta.display().to_string()))
+}
+
+fn write_sketches(
+    writer: &mut OutputWriter,
+    params: &SketchParams,
+    sketches: &[Sketch],
+) -> Result<(), AppError> {
+    writeln!(writer, "##kmer_size={}", params.kmer_size)?;
+
+    match params.scheme {
+        SketchScheme::MinHash { size } => {
+            writeln!(writer, "##scheme=minhash")?;
+            writeln!(writer, "##sketch_size={size}")?;
+        }
+        SketchScheme::FracMinHash { scaled } => {
+            writeln!(writer, "##scheme=fracminhash")?;
+            writeln!(writer, "##scaled={scaled}")?;
+        }
+    }
+
+    writeln!(writer, "#name\tlength\thashes")?;
+
+    for sketch in sketches {
+        let hashes: Vec<String> = sketch.hashes.iter().map(|hash| hash.to_string()).collect();
+        writeln!(
+            writer,
+            "{}\t{}\t{}",
+            sketch.name,
+            sketch.length,
+            hashes.join(",")
+        )?;
+    }
+
+    Ok(())
+}
+
+fn parse_params(lines: &[String]) -> Result<SketchParams, AppError> {
+    let value = |key: &str| -> Result<&str, AppError> {
+        lines
+            .iter()
+            .find_map(|line| line.strip_prefix(&format!("##{key}=")))
+            .ok_or_else(|| AppError::InvalidArgError(format!("sketch file is missing {key}")))
+    };
+
+    let scheme = match value("scheme")? {
+        "minhash" => SketchScheme::MinHash {
+            size: value("sketch_size")?.parse()?,
+        },
+        "fracminhash" => SketchScheme::FracMinHash {
+            scaled: value("scaled")?.parse()?,
+        },
+        scheme => {
+            return Err(AppError::InvalidArgError(format!(
+                "invalid sketch scheme: {scheme}"
+            )));
+        }
+    };
+
+    Ok(SketchParams {
+        kmer_size: value("kmer_size")?.parse()?,
+        scheme,
+    })
+}
+
+fn parse_sketch(line: &str) -> Result<Sketch, AppError> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != 3 {
+        return Err(AppError::InvalidArgError(format!(
+            "invalid sketch line: {line}"
+        )));
+    }
+
+    let hashes = match fields[2].is_empty() {
+        true => Vec::new(),
+        false => fields[2]
+            .split(',')
+            .map(|hash| hash.parse())
+            .collect::<Result<Vec<u64>, _>>()?,
+    };
+
+    Ok(Sketch {
+        name: fields[0].to_string(),
+        length: fields[1].parse()?,
+        hashes,
+    })
+}
+
+/// Sketches of a file written by `sketch`.
+pub fn read_sketches(path: &Path) -> Result<(SketchParams, Vec<Sketch>), AppError> {
+    let reader = open_input(Some(path))?;
+
+    let mut param_lines: Vec<String> = Vec::new();
+    let mut sketches: Vec<Sketch> = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if line.starts_with("##") {
+            param_lines.push(line);
+        } else if !line.is_empty() && !line.starts_with('#') {
+            sketches.push(parse_sketch(&line)?);
+        }
+    }
+
+    Ok((parse_params(&param_lines)?, sketches))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn fasta_sketch(
+    fasta: Vec<PathBuf>,
+    kmer_size: usize,
+    sketch_size: usize,
+    scaled: Option<u64>,
+    per_record: bool,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    if !(1..=MAX_KMER_SIZE).contains(&kmer_size) {
+        return Err(AppError::InvalidArgError(format!(
+            "kmer size must be 1-{MAX_KMER_SIZE}"
+        )));
+    }
+
+    let scheme = match scaled {
+        Some(0) => return Err(AppError::InvalidArgError("scaled must be > 0".into())),
+        Some(scaled) => SketchScheme::FracMinHash { scaled },
+        None if sketch_size == 0 => {
+            return Err(AppError::InvalidArgError("sketch size must be > 0".into()));
+        }
+        None => SketchScheme::MinHash { size: sketch_size },
+    };
+
+    let params = SketchParams { kmer_size, scheme };
+
+    // Records are sketched in parallel within each file, files in parallel otherwise.
+    let sketches: Vec<Sketch> = match per_record {
+        true => fasta
+            .iter()
+            .map(|fasta| sketch_records(fasta, params))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect(),
+        false => fasta
+            .par_iter()
+            .map(|fasta| sketch_file(fasta, params))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let mut writer = get_writer(outfile, writer_opts)?;
+    write_sketches(&mut writer, &params, &sketches)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(SketchScheme::MinHash { size: 2 }, vec![5, 3, 9, 3, 1], vec![1, 3])]
+#[case(SketchScheme::MinHash { size: 5 }, vec![5, 3, 9, 3], vec![3, 5, 9])]
+#[case(SketchScheme::FracMinHash { scaled: 1 }, vec![5, 3, u64::MAX], vec![3, 5, u64::MAX])]
+#[case(SketchScheme::FracMinHash { scaled: 4 }, vec![5, u64::MAX / 4 + 1, u64::MAX], vec![5])]
+
+fn test_sketch_builder(
+    #[case] scheme: SketchScheme,
+    #[case] hashes: Vec<u64>,
+    #[case] expected: Vec<u64>,
+) {
+    let mut builder = SketchBuilder::new(SketchParams {
+        kmer_size: 21,
+        scheme,
+    });
+
+    for hash in hashes {
+        builder.insert(hash);
+    }
+
+    assert_eq!(builder.build("a".to_string()).hashes, expected);
+}
+
+#[rstest]
+#[case("a\t10\t1,2,3", Sketch { name: "a".into(), length: 10, hashes: vec![1, 2, 3] })]
+#[case("b c\t3\t", Sketch { name: "b c".into(), length: 3, hashes: vec![] })]
+
+fn test_parse_sketch(#[case] line: &str, #[case] expected: Sketch) {
+    assert_eq!(parse_sketch(line).unwrap(), expected);
+}
+
+#[rstest]
+#[case(None)]
+#[case(Some(CompressType::Gzip))]
+#[case(Some(CompressType::Bgzip))]
+#[case(Some(CompressType::Zstd))]
+#[case(Some(CompressType::Xz))]
+
+fn test_sketches_roundtrip(#[case] compress: Option<CompressType>) {
+    let params = SketchParams {
+        kmer_size: 21,
+        scheme: SketchScheme::FracMinHash { scaled: 10 },
+    };
+    let sketches = vec![Sketch {
+        name: "a".into(),
+        length: 10,
+        hashes: vec![1, 2, 3],
+    }];
+
+    let (_dir, path) = write_temp_file("sketches.tsv", compress, |writer| {
+        write_sketches(writer, &params, &sketches)
+    });
+
+    assert_eq!(read_sketches(&path).unwrap(), (params, sketches));
+}
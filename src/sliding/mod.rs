@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod sliding;
+pub use sliding::fasta_sliding;
@@ -0,0 +1,117 @@
+use crate::errors::AppError;
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader};
+use rstest::*;
+use std::path::PathBuf;
+
+/// Start and end of each full window along a sequence of length `seq_len`. For circular
+/// sequences, windows start at every step and ends past `seq_len` wrap around the origin.
+fn sliding_windows(
+    seq_len: usize,
+    window: usize,
+    step: usize,
+    circular: bool,
+) -> Vec<(usize, usize)> {
+    if window > seq_len {
+        return Vec::new();
+    }
+
+    let last_start = match circular {
+        true => seq_len - 1,
+        false => seq_len - window,
+    };
+
+    (0..=last_start)
+        .step_by(step)
+        .map(|start| (start, start + window))
+        .collect()
+}
+
+/// Piece of `seq` in `start..end`, where `end` may wrap around the origin.
+fn piece(seq: &[u8], start: usize, end: usize) -> Vec<u8> {
+    match end > seq.len() {
+        true => [&seq[start..], &seq[..end - seq.len()]].concat(),
+        false => seq[start..end].to_vec(),
+    }
+}
+
+pub fn fasta_sliding(
+    fasta: Option<PathBuf>,
+    window: usize,
+    step: Option<usize>,
+    circular: bool,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let step = step.unwrap_or(window);
+
+    if window == 0 || step == 0 {
+        return Err(AppError::InvalidArgError(
+            "window and step must be > 0".into(),
+        ));
+    }
+
+    let mut reader = fastx_reader(fasta)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        let record_seq = record.seq();
+        let record_qual = record.qual();
+
+        let header = Header::parse(record.id());
+        let record_id = std::str::from_utf8(header.id)?;
+
+        for (start, end) in sliding_windows(record_seq.len(), window, step, circular) {
+            // Add start/end coordinates, where an end before the start wraps around the origin.
+            let id = match end > record_seq.len() {
+                true => format!("{}|{}-{}", record_id, start, end - record_seq.len()),
+                false => format!("{}|{}-{}", record_id, start, end),
+            };
+
+            writer.write(
+                &header.with_id(id.as_bytes()),
+                &piece(&record_seq, start, end),
+                record_qual.map(|qual| piece(qual, start, end)).as_deref(),
+            )?;
+        }
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(10, 4, 3, false, vec![(0, 4), (3, 7), (6, 10)])]
+#[case(10, 4, 4, false, vec![(0, 4), (4, 8)])]
+#[case(10, 4, 4, true, vec![(0, 4), (4, 8), (8, 12)])]
+#[case(4, 4, 1, true, vec![(0, 4), (1, 5), (2, 6), (3, 7)])]
+#[case(3, 4, 1, false, vec![])]
+#[case(3, 4, 1, true, vec![])]
+
+fn test_sliding_windows(
+    #[case] seq_len: usize,
+    #[case] window: usize,
+    #[case] step: usize,
+    #[case] circular: bool,
+    #[case] expected: Vec<(usize, usize)>,
+) {
+    assert_eq!(sliding_windows(seq_len, window, step, circular), expected);
+}
+
+#[rstest]
+#[case(b"ACGTAC", 1, 4, b"CGT")]
+#[case(b"ACGTAC", 4, 8, b"ACAC")]
+
+fn test_piece(
+    #[case] seq: &[u8],
+    #[case] start: usize,
+    #[case] end: usize,
+    #[case] expected: &[u8],
+) {
+    assert_eq!(piece(seq, start, end), expected);
+}
@@ -1,7 +1,6 @@
 use crate::args::SortType;
 use crate::errors::AppError;
-use bio::io::fasta::Record;
-use bio_utils_rs::io::{bio_fasta_reader, bio_fasta_writer};
+use crate::fastx::{FastxWriter, OwnedRecord, WriterOpts, read_records};
 use bio_utils_rs::nucleotide::{
     gc_content, nucleotide_counts, nucleotide_probabilities, shannon_entropy,
 };
@@ -12,7 +11,7 @@ use std::path::PathBuf;
 
 #[derive(Debug, Serialize)]
 struct FastaRecord {
-    record: Record,
+    record: OwnedRecord,
     gc: f64,
     entropy: f32,
     softmask_count: usize,
@@ -30,13 +29,13 @@ fn sort_records(fasta_records: &mut [FastaRecord], sort_type: SortType, reverse:
     match sort_type {
         SortType::Length => {
             fasta_records.par_sort_by(|a, b| {
-                let ord = a.record.seq().len().cmp(&b.record.seq().len());
+                let ord = a.record.seq.len().cmp(&b.record.seq.len());
                 ascending_or_descending(ord, reverse)
             });
         }
         SortType::Id => {
             fasta_records.par_sort_by(|a: &FastaRecord, b| {
-                let ord = a.record.id().cmp(b.record.id());
+                let ord = a.record.header().id.cmp(b.record.header().id);
                 ascending_or_descending(ord, reverse)
             });
         }
@@ -75,16 +74,12 @@ pub fn fasta_sort(
     sort_type: SortType,
     reverse: bool,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<(), AppError> {
-    let reader = bio_fasta_reader(fasta)?;
-
-    let mut fasta_records: Vec<FastaRecord> = reader
-        .records()
-        .par_bridge()
-        .filter_map(|record| {
-            let record = record.ok()?;
-
-            let record_seq = record.seq();
+    let mut fasta_records: Vec<FastaRecord> = read_records(fasta)?
+        .into_par_iter()
+        .map(|record| {
+            let record_seq = &record.seq;
 
             // GC count
             let gc = gc_content(record_seq);
@@ -94,27 +89,26 @@ pub fn fasta_sort(
             let probs = nucleotide_probabilities(&canonical);
             let entropy = shannon_entropy(&probs);
 
-            let fasta_record = FastaRecord {
+            FastaRecord {
                 record,
                 gc,
                 entropy,
                 softmask_count,
                 ambiguous_count,
-            };
-
-            Some(fasta_record)
+            }
         })
         .collect();
 
     sort_records(&mut fasta_records, sort_type, reverse);
 
-    let mut writer = bio_fasta_writer(outfile)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
 
     for fasta_record in fasta_records {
-        writer.write_record(&fasta_record.record)?;
+        let record = &fasta_record.record;
+        writer.write(&record.header(), &record.seq, record.qual())?;
     }
 
-    writer.flush()?;
+    writer.finish()?;
 
     Ok(())
 }
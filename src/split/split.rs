@@ -1,10 +1,14 @@
 use crate::errors::AppError;
-use bio_utils_rs::io::{get_bufwriter, needletail_reader};
+use crate::fastx::{FastxWriter, Header, WriterOpts, fastx_reader, format_extension};
 use std::fs::create_dir_all;
 use std::path::PathBuf;
 
-pub fn fasta_split(fasta: Option<PathBuf>, outdir: &PathBuf) -> Result<(), AppError> {
-    let mut reader = needletail_reader(fasta)?;
+pub fn fasta_split(
+    fasta: Option<PathBuf>,
+    outdir: &PathBuf,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let mut reader = fastx_reader(fasta)?;
 
     create_dir_all(outdir)?;
 
@@ -14,13 +18,18 @@ pub fn fasta_split(fasta: Option<PathBuf>, outdir: &PathBuf) -> Result<(), AppEr
             Err(_) => continue,
         };
 
+        let header = Header::parse(record.id());
+
         // Define output fasta file.
-        let outfile = outdir.join(format!("{}.fasta", std::str::from_utf8(record.id())?));
+        let outfile = outdir.join(writer_opts.file_name(
+            std::str::from_utf8(header.id)?,
+            format_extension(record.format()),
+        ));
 
-        let mut writer = get_bufwriter(Some(outfile))?;
-        record.write(&mut writer, None)?;
+        let mut writer = FastxWriter::new(Some(outfile), writer_opts)?;
+        writer.write(&header, &record.seq(), record.qual())?;
 
-        writer.flush()?;
+        writer.finish()?;
     }
 
     Ok(())
@@ -1,5 +1,5 @@
 use crate::errors::AppError;
-use bio_utils_rs::io::{needletail_reader, write_json};
+use crate::fastx::{WriterOpts, fastx_reader, get_writer};
 use serde::Serialize;
 use std::path::PathBuf;
 
@@ -15,8 +15,9 @@ pub struct FastaStats {
 pub fn fasta_stats(
     fasta: Option<PathBuf>,
     outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
 ) -> Result<FastaStats, AppError> {
-    let mut reader = needletail_reader(fasta)?;
+    let mut reader = fastx_reader(fasta)?;
 
     let mut num_seqs = 0;
     let mut num_bases = 0;
@@ -46,7 +47,9 @@ pub fn fasta_stats(
         max_len,
     };
 
-    write_json(outfile, &fasta_stats)?;
+    let mut writer = get_writer(outfile, writer_opts)?;
+    serde_json::to_writer(&mut writer, &fasta_stats)?;
+    writer.finish()?;
 
     Ok(fasta_stats)
 }
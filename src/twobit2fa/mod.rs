@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod twobit2fa;
+pub use twobit2fa::fasta_twobit2fa;
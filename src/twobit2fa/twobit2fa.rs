@@ -0,0 +1,21 @@
+use crate::errors::AppError;
+use crate::fastx::{FastxWriter, Header, TwoBitFile, WriterOpts};
+use std::path::{Path, PathBuf};
+
+pub fn fasta_twobit2fa(
+    twobit: &Path,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let twobit = TwoBitFile::open(twobit)?;
+    let mut writer = FastxWriter::new(outfile, writer_opts)?;
+
+    for record in twobit.sequences() {
+        let (name, seq) = record?;
+        writer.write(&Header::parse(name.as_bytes()), &seq, None)?;
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod window;
+pub use window::fasta_window;
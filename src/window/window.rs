@@ -0,0 +1,195 @@
+use crate::args::{WindowFormat, WindowMetric};
+use crate::errors::AppError;
+use crate::fastx::{Header, WriterOpts, fastx_reader, get_writer};
+use bio_utils_rs::nucleotide::{
+    gc_content, nucleotide_counts, nucleotide_probabilities, shannon_entropy,
+};
+use rstest::*;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Metrics of a single window. Softmasked and ambiguous bases are fractions of the window.
+#[derive(Debug, PartialEq)]
+struct WindowMetrics {
+    gc: f64,
+    entropy: f32,
+    softmasked: f64,
+    ambiguous: f64,
+    gc_skew: f64,
+    cumulative_gc_skew: f64,
+}
+
+impl WindowMetrics {
+    fn get(&self, metric: &WindowMetric) -> f64 {
+        match metric {
+            WindowMetric::Gc => self.gc,
+            WindowMetric::Entropy => self.entropy as f64,
+            WindowMetric::Softmask => self.softmasked,
+            WindowMetric::Ambiguous => self.ambiguous,
+            WindowMetric::GcSkew => self.gc_skew,
+            WindowMetric::CumulativeGcSkew => self.cumulative_gc_skew,
+        }
+    }
+}
+
+/// GC skew `(G - C) / (G + C)`, case-insensitive. 0 without G or C.
+fn gc_skew(seq: &[u8]) -> f64 {
+    let g = seq.iter().filter(|nt| matches!(nt, b'G' | b'g')).count() as f64;
+    let c = seq.iter().filter(|nt| matches!(nt, b'C' | b'c')).count() as f64;
+
+    match g + c {
+        0.0 => 0.0,
+        gc => (g - c) / gc,
+    }
+}
+
+/// Window coordinates along a sequence of length `seq_len`. The last window may be shorter,
+/// and windows stop once the end of the sequence is reached.
+fn windows(seq_len: usize, window: usize, step: usize) -> Vec<(usize, usize)> {
+    let mut windows: Vec<(usize, usize)> = Vec::new();
+
+    let mut start = 0;
+    while start < seq_len {
+        let end = (start + window).min(seq_len);
+        windows.push((start, end));
+
+        if end == seq_len {
+            break;
+        }
+
+        start += step;
+    }
+
+    windows
+}
+
+fn window_metrics(seq: &[u8], cumulative_gc_skew: &mut f64) -> WindowMetrics {
+    let (canonical, num_softmasked, num_ambiguous) = nucleotide_counts(seq);
+    let probs = nucleotide_probabilities(&canonical);
+
+    let skew = gc_skew(seq);
+    *cumulative_gc_skew += skew;
+
+    WindowMetrics {
+        gc: gc_content(seq),
+        // Avoids -0 for windows with a single distinct base.
+        entropy: shannon_entropy(&probs).abs(),
+        softmasked: num_softmasked as f64 / seq.len() as f64,
+        ambiguous: num_ambiguous as f64 / seq.len() as f64,
+        gc_skew: skew,
+        cumulative_gc_skew: *cumulative_gc_skew,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn fasta_window(
+    fasta: Option<PathBuf>,
+    window: usize,
+    step: Option<usize>,
+    output_format: &WindowFormat,
+    metric: &WindowMetric,
+    outfile: Option<PathBuf>,
+    writer_opts: &WriterOpts,
+) -> Result<(), AppError> {
+    let step = step.unwrap_or(window);
+
+    if window == 0 || step == 0 {
+        return Err(AppError::InvalidArgError(
+            "window and step must be > 0".into(),
+        ));
+    }
+
+    let mut reader = fastx_reader(fasta)?;
+
+    // Output file writer.
+    let mut writer = get_writer(outfile, writer_opts)?;
+
+    if let WindowFormat::Tsv = output_format {
+        writeln!(
+            writer,
+            "contig\tstart\tend\tgc_content\tentropy\tsoftmasked\tambiguous\tgc_skew\tcumulative_gc_skew"
+        )?;
+    }
+
+    while let Some(record) = reader.next() {
+        let record = match record {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+
+        let header = Header::parse(record.id());
+        let seq = record.seq();
+
+        let mut cumulative_gc_skew = 0.0;
+
+        for (start, end) in windows(seq.len(), window, step) {
+            let metrics = window_metrics(&seq[start..end], &mut cumulative_gc_skew);
+
+            writer.write_all(header.id)?;
+
+            match output_format {
+                WindowFormat::Bedgraph => {
+                    writeln!(writer, "\t{}\t{}\t{:.4}", start, end, metrics.get(metric))?
+                }
+                WindowFormat::Tsv => writeln!(
+                    writer,
+                    "\t{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{:.4}\t{:.4}\t{:.4}",
+                    start,
+                    end,
+                    metrics.gc,
+                    metrics.entropy,
+                    metrics.softmasked,
+                    metrics.ambiguous,
+                    metrics.gc_skew,
+                    metrics.cumulative_gc_skew
+                )?,
+            }
+        }
+    }
+
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(0, 10, 10, vec![])]
+#[case(25, 10, 10, vec![(0, 10), (10, 20), (20, 25)])]
+#[case(20, 10, 10, vec![(0, 10), (10, 20)])]
+#[case(20, 10, 5, vec![(0, 10), (5, 15), (10, 20)])]
+#[case(5, 10, 5, vec![(0, 5)])]
+
+fn test_windows(
+    #[case] seq_len: usize,
+    #[case] window: usize,
+    #[case] step: usize,
+    #[case] expected: Vec<(usize, usize)>,
+) {
+    assert_eq!(windows(seq_len, window, step), expected);
+}
+
+#[rstest]
+#[case(b"ATAT", 0.0)]
+#[case(b"GGGC", 0.5)]
+#[case(b"gCCC", -0.5)]
+
+fn test_gc_skew(#[case] seq: &[u8], #[case] expected: f64) {
+    assert_eq!(gc_skew(seq), expected);
+}
+
+#[rstest]
+fn test_window_metrics() {
+    let mut cumulative_gc_skew = 0.5;
+
+    assert_eq!(
+        window_metrics(b"GGacNN", &mut cumulative_gc_skew),
+        WindowMetrics {
+            gc: 0.5,
+            entropy: 0.0,
+            softmasked: 2.0 / 6.0,
+            ambiguous: 2.0 / 6.0,
+            gc_skew: 1.0 / 3.0,
+            cumulative_gc_skew: 0.5 + 1.0 / 3.0,
+        }
+    );
+}